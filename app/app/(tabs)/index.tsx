@@ -27,7 +27,7 @@ const schema = z.object({
 	position: z.number(),
 	title: z.string(),
 	file: z.string(),
-	subtitle: z.string(),
+	subtitle: z.number().nullable(),
 	subtitles: z.array(z.object({ id: z.number(), title: z.string() })),
 });
 
@@ -92,9 +92,9 @@ export default function HomePage() {
 	});
 
 	React.useEffect(() => {
-		if (status?.subtitle) {
-			const value = status?.subtitles?.find((s) => s.id.toString() === status?.subtitle);
-			setSubtitle({ label: value?.title || 'None', value: status?.subtitle });
+		if (status) {
+			const value = status.subtitles.find((s) => s.id === status.subtitle);
+			setSubtitle({ label: value?.title || 'None', value: status.subtitle?.toString() ?? '' });
 		}
 	}, [status?.subtitle]);
 
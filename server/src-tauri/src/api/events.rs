@@ -0,0 +1,31 @@
+use std::convert::Infallible;
+
+use futures_util::stream;
+use salvo::sse::{SseEvent, SseKeepAlive};
+use salvo::{handler, Request, Response};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::clients;
+
+#[handler]
+pub async fn handle_events(_req: &mut Request, res: &mut Response) {
+    tracing::debug!("[API: SSE] Client subscribed to events");
+
+    let events = stream::unfold(clients::mpv::subscribe_events(), |mut events| async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    let data = serde_json::to_string(&event).unwrap_or_default();
+                    let event = SseEvent::default().name(event.name()).text(data);
+                    return Some((Ok::<_, Infallible>(event), events));
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::debug!("[API: SSE] Client skipped {} events", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    SseKeepAlive::new(events).stream(res);
+}
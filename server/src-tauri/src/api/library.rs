@@ -0,0 +1,94 @@
+use salvo::{
+    handler,
+    writing::{Json, Text},
+    Depot, Request, Response,
+};
+use serde_json::json;
+
+use crate::clients::{
+    self,
+    mpv::{Error, LoadMode},
+};
+use crate::library::{self, Kind, Query, Sort};
+
+/// Lists `path`, or the media roots without it.
+///
+/// `kinds` is a comma separated filter like `video,subtitle`, `sort` is one of
+/// `name` (the default), `size` or `modified`, `order=desc` reverses it.
+/// `offset` and `limit` page through the entries.
+#[handler]
+pub async fn handle_list_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let path = req.query::<String>("path");
+
+    let kinds = match req.query::<String>("kinds") {
+        Some(kinds) => kinds
+            .split(',')
+            .map(|kind| {
+                serde_json::from_value::<Kind>(json!(kind))
+                    .map_err(|_| Error::BadArgument(format!("Unknown kind: {}", kind)))
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        None => vec![],
+    };
+    let sort = match req.query::<String>("sort") {
+        Some(sort) => serde_json::from_value::<Sort>(json!(sort))
+            .map_err(|_| Error::BadArgument(format!("Unknown sort: {}", sort)))?,
+        None => Sort::Name,
+    };
+    let query = Query {
+        kinds,
+        sort,
+        descending: req.query::<String>("order").as_deref() == Some("desc"),
+        offset: req.query::<usize>("offset").unwrap_or(0),
+        limit: req.query::<usize>("limit").unwrap_or(100),
+    };
+    tracing::debug!("[API: Library] Listing {:?}", path);
+
+    let listing = library::list(path.as_deref(), &query)?;
+    res.render(Json(listing));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_search_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let query = req
+        .query::<String>("q")
+        .ok_or_else(|| Error::BadArgument("No search query provided".to_string()))?;
+    let limit = req.query::<usize>("limit").unwrap_or(50);
+    tracing::debug!("[API: Library] Searching for {}", query);
+
+    res.render(Json(library::search(&query, limit)));
+    Ok(())
+}
+
+/// Hands a file from the library to mpv, `mode` works like `/mpv/loadfile`.
+#[handler]
+pub async fn handle_play_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let path = req
+        .query::<String>("path")
+        .ok_or_else(|| Error::BadArgument("No path provided".to_string()))?;
+    let mode = match req.query::<String>("mode") {
+        Some(mode) => serde_json::from_value::<LoadMode>(json!(mode))
+            .map_err(|_| Error::BadArgument(format!("Unknown load mode: {}", mode)))?,
+        None => LoadMode::Replace,
+    };
+    tracing::debug!("[API: Library] Playing {} ({:?})", path, mode);
+
+    // Checked here too so a URL can't sneak in through the library.
+    let path = library::resolve(&path)?;
+    clients::mpv::loadfile(&path.to_string_lossy(), mode).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
@@ -1,4 +1,8 @@
+mod events;
+mod library;
 pub mod mpv;
+mod players;
+mod ws;
 
 use salvo::prelude::*;
 use salvo::server::ServerHandle;
@@ -21,18 +25,108 @@ pub async fn init() {
         .push(Router::with_path("ping").get(ping))
         .push(Router::with_path("stop").get(stop))
         .push(Router::with_path("restart").get(restart))
-        .push(Router::with_path("mpv/status").get(mpv::handle_status_api))
-        .push(Router::with_path("mpv/set-volume").get(mpv::handle_set_volume_api))
-        .push(Router::with_path("mpv/volume-up").get(mpv::handle_volume_up_api))
-        .push(Router::with_path("mpv/volume-down").get(mpv::handle_volume_down_api))
-        .push(Router::with_path("mpv/skip-backward").get(mpv::handle_skip_backward_api))
-        .push(Router::with_path("mpv/skip-forward").get(mpv::handle_skip_forward_api))
-        .push(Router::with_path("mpv/subtitle").get(mpv::handle_set_subtitle))
-        .push(Router::with_path("mpv/pause").get(mpv::handle_pause_api));
+        .push(Router::with_path("events").get(events::handle_events))
+        .push(mpv_routes(Router::with_path("mpv")))
+        .push(Router::with_path("mpv/instances").get(mpv::handle_instances_api))
+        .push(
+            mpv_routes(Router::with_path("mpv/instances/<instance>"))
+                .hoop(mpv::scope_instance)
+                .push(Router::with_path("events").get(events::handle_events))
+                .push(Router::with_path("ws").get(ws::handle_ws)),
+        )
+        .push(Router::with_path("screenshots").get(mpv::handle_screenshots_api))
+        .push(
+            Router::with_path("screenshots/<name>")
+                .get(mpv::handle_get_screenshot_api)
+                .delete(mpv::handle_delete_screenshot_api),
+        )
+        .push(Router::with_path("library").get(library::handle_list_api))
+        .push(Router::with_path("library/play").get(library::handle_play_api))
+        .push(Router::with_path("library/search").get(library::handle_search_api))
+        .push(Router::with_path("players").get(players::handle_list_api))
+        .push(
+            Router::with_path("players/<id>")
+                .push(Router::with_path("status").get(players::handle_status_api))
+                .push(Router::with_path("pause").get(players::handle_pause_api))
+                .push(Router::with_path("seek").get(players::handle_seek_api))
+                .push(Router::with_path("set-volume").get(players::handle_set_volume_api))
+                .push(Router::with_path("volume-up").get(players::handle_volume_up_api))
+                .push(Router::with_path("volume-down").get(players::handle_volume_down_api))
+                .push(Router::with_path("subtitle").get(players::handle_set_subtitle_api))
+                .push(Router::with_path("subtitle/off").get(players::handle_subtitle_off_api))
+                .push(Router::with_path("audio").get(players::handle_set_audio_api))
+                .push(Router::with_path("video").get(players::handle_set_video_api))
+                .push(Router::with_path("playlist").get(players::handle_playlist_api))
+                .push(Router::with_path("next").get(players::handle_next_api))
+                .push(Router::with_path("previous").get(players::handle_previous_api))
+                .push(Router::with_path("wait").get(players::handle_wait_api)),
+        )
+        .push(Router::with_path("ws").get(ws::handle_ws));
+
+    #[cfg(target_os = "linux")]
+    let router =
+        router.push(Router::with_path("mpris/players").get(players::handle_mpris_list_api));
 
     server.serve(router).await;
 }
 
+/// Routes controlling one mpv instance, mounted for the default instance and
+/// for each named one.
+fn mpv_routes(router: Router) -> Router {
+    router
+        .push(Router::with_path("status").get(mpv::handle_status_api))
+        .push(Router::with_path("start").get(mpv::handle_start_api))
+        .push(Router::with_path("quit").get(mpv::handle_quit_api))
+        .push(Router::with_path("set-volume").get(mpv::handle_set_volume_api))
+        .push(Router::with_path("volume-up").get(mpv::handle_volume_up_api))
+        .push(Router::with_path("volume-down").get(mpv::handle_volume_down_api))
+        .push(Router::with_path("volume/limits").get(mpv::handle_volume_limits_api))
+        .push(Router::with_path("volume/night-mode").get(mpv::handle_night_mode_api))
+        .push(Router::with_path("volume/normalize").get(mpv::handle_normalize_api))
+        .push(Router::with_path("mute").get(mpv::handle_mute_api))
+        .push(Router::with_path("skip-backward").get(mpv::handle_skip_backward_api))
+        .push(Router::with_path("skip-forward").get(mpv::handle_skip_forward_api))
+        .push(Router::with_path("seek").get(mpv::handle_seek_api))
+        .push(Router::with_path("speed").get(mpv::handle_speed_api))
+        .push(Router::with_path("speed/presets").get(mpv::handle_speed_presets_api))
+        .push(Router::with_path("speed/cycle").get(mpv::handle_speed_cycle_api))
+        .push(Router::with_path("speed/pitch-correction").get(mpv::handle_pitch_correction_api))
+        .push(Router::with_path("ab-loop").get(mpv::handle_ab_loop_api))
+        .push(Router::with_path("ab-loop/clear").get(mpv::handle_ab_loop_clear_api))
+        .push(Router::with_path("ab-loop/cycle").get(mpv::handle_ab_loop_cycle_api))
+        .push(Router::with_path("chapter").get(mpv::handle_chapter_api))
+        .push(Router::with_path("chapter/next").get(mpv::handle_chapter_next_api))
+        .push(Router::with_path("chapter/previous").get(mpv::handle_chapter_previous_api))
+        .push(Router::with_path("screenshot").get(mpv::handle_screenshot_api))
+        .push(Router::with_path("loadfile").get(mpv::handle_loadfile_api))
+        .push(Router::with_path("playlist").get(mpv::handle_playlist_api))
+        .push(Router::with_path("playlist/play").get(mpv::handle_playlist_play_api))
+        .push(Router::with_path("playlist/next").get(mpv::handle_playlist_next_api))
+        .push(Router::with_path("playlist/previous").get(mpv::handle_playlist_previous_api))
+        .push(Router::with_path("playlist/remove").get(mpv::handle_playlist_remove_api))
+        .push(Router::with_path("playlist/move").get(mpv::handle_playlist_move_api))
+        .push(Router::with_path("playlist/shuffle").get(mpv::handle_playlist_shuffle_api))
+        .push(Router::with_path("playlist/unshuffle").get(mpv::handle_playlist_unshuffle_api))
+        .push(Router::with_path("playlist/clear").get(mpv::handle_playlist_clear_api))
+        .push(Router::with_path("playlist/loop-file").get(mpv::handle_loop_file_api))
+        .push(Router::with_path("playlist/loop-playlist").get(mpv::handle_loop_playlist_api))
+        .push(Router::with_path("subtitle").get(mpv::handle_set_subtitle))
+        .push(Router::with_path("subtitle/off").get(mpv::handle_subtitle_off_api))
+        .push(
+            Router::with_path("subtitle/add")
+                .get(mpv::handle_add_subtitle_api)
+                .post(mpv::handle_add_subtitle_api),
+        )
+        .push(Router::with_path("subtitle/secondary").get(mpv::handle_secondary_subtitle_api))
+        .push(Router::with_path("subtitle/delay").get(mpv::handle_subtitle_delay_api))
+        .push(Router::with_path("subtitle/scale").get(mpv::handle_subtitle_scale_api))
+        .push(Router::with_path("subtitle/position").get(mpv::handle_subtitle_position_api))
+        .push(Router::with_path("subtitle/visibility").get(mpv::handle_subtitle_visibility_api))
+        .push(Router::with_path("audio").get(mpv::handle_set_audio_api))
+        .push(Router::with_path("video").get(mpv::handle_set_video_api))
+        .push(Router::with_path("pause").get(mpv::handle_pause_api))
+}
+
 #[handler]
 async fn ping(_req: &mut Request, res: &mut Response, _depot: &mut Depot) {
     res.render(Text::Plain("pong"));
@@ -1,82 +1,851 @@
-use salvo::{handler, writing::Text, Depot, Request, Response};
+use salvo::{
+    handler,
+    http::{
+        header::{HeaderValue, CONTENT_TYPE},
+        ResBody, StatusCode,
+    },
+    writing::{Json, Text},
+    Depot, FlowCtrl, Request, Response, Scribe,
+};
+use serde_json::json;
+use std::path::PathBuf;
 
-use crate::clients;
+use crate::clients::{
+    self,
+    mpv::{Error, ImageFormat, LoadMode, SeekMode},
+};
+use crate::settings::load_settings;
+
+impl Scribe for Error {
+    fn render(self, res: &mut Response) {
+        tracing::debug!("[API: MPV] {}", self);
+        res.status_code(match self {
+            Error::NotRunning => StatusCode::SERVICE_UNAVAILABLE,
+            Error::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            Error::Protocol(_) => StatusCode::BAD_GATEWAY,
+            Error::PropertyUnavailable(_) => StatusCode::CONFLICT,
+            Error::BadArgument(_) => StatusCode::BAD_REQUEST,
+        });
+        res.render(Json(
+            json!({ "error": self.kind(), "message": self.to_string() }),
+        ));
+    }
+}
+
+/// Runs the rest of the request against the instance named in the path.
+#[handler]
+pub async fn scope_instance(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    ctrl: &mut FlowCtrl,
+) {
+    let name = req.param::<String>("instance").unwrap_or_default();
+    if !clients::mpv::instance_exists(&name) {
+        res.render(Error::BadArgument(format!(
+            "Unknown mpv instance: {}",
+            name
+        )));
+        ctrl.skip_rest();
+        return;
+    }
+
+    clients::mpv::scope(name, ctrl.call_next(req, depot, res)).await;
+}
+
+#[handler]
+pub async fn handle_instances_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Listing instances");
+    res.render(Json(clients::mpv::list_instances().await));
+    Ok(())
+}
 
 #[tauri::command]
-pub async fn handle_pause_cmd() {
+pub async fn handle_pause_cmd() -> Result<(), Error> {
     tracing::debug!("[Tauri] Toggling pause");
-    clients::mpv::toggle_pause().await;
+    clients::mpv::toggle_pause().await
 }
 
 #[handler]
-pub async fn handle_pause_api(_req: &mut Request, res: &mut Response, _depot: &mut Depot) {
+pub async fn handle_pause_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
     tracing::debug!("[API: MPV] Toggling pause");
-    clients::mpv::toggle_pause().await;
-    res.render(Text::Plain("ok"))
+    clients::mpv::toggle_pause().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
 }
 
 #[handler]
-pub async fn handle_set_subtitle(req: &mut Request, res: &mut Response, _depot: &mut Depot) {
-    let id = req.query::<i64>("id").expect("No subtitle ID provided");
+pub async fn handle_set_subtitle(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let id = req
+        .query::<i64>("id")
+        .ok_or_else(|| Error::BadArgument("No subtitle ID provided".to_string()))?;
     tracing::debug!("[API: MPV] Setting subtitle to {}", id);
 
-    clients::mpv::set_subtitle(id).await;
-    res.render(Text::Plain("ok"))
+    clients::mpv::set_subtitle(id).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_subtitle_off_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Disabling subtitles");
+    clients::mpv::disable_subtitles().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_secondary_subtitle_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    // Leaving out the ID turns the secondary subtitle off.
+    let id = req.query::<i64>("id");
+    tracing::debug!("[API: MPV] Setting secondary subtitle to {:?}", id);
+
+    clients::mpv::set_secondary_subtitle(id).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+/// Loads an external subtitle, either a `path` on this machine or a
+/// multipart `file` upload. `select=false` adds it without switching to it.
+#[handler]
+pub async fn handle_add_subtitle_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let select = req.query::<bool>("select").unwrap_or(true);
+
+    let path = if let Some(path) = req.query::<String>("path") {
+        PathBuf::from(path)
+    } else {
+        let file = req
+            .file("file")
+            .await
+            .ok_or_else(|| Error::BadArgument("No path or file provided".to_string()))?;
+        if file.size() > clients::mpv::MAX_SUBTITLE_SIZE {
+            return Err(Error::BadArgument(format!(
+                "Subtitle file is too large: {} bytes",
+                file.size()
+            )));
+        }
+        let name = file
+            .name()
+            .ok_or_else(|| Error::BadArgument("Upload has no file name".to_string()))?;
+        clients::mpv::store_subtitle(name, file.path())?
+    };
+    tracing::debug!("[API: MPV] Adding subtitle {}", path.display());
+
+    let subtitles = clients::mpv::add_subtitle(&path, select).await?;
+    res.render(Json(subtitles));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_subtitle_delay_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    if let Some(delay) = req.query::<f64>("set") {
+        tracing::debug!("[API: MPV] Setting subtitle delay to {}", delay);
+        clients::mpv::set_subtitle_delay(delay).await?;
+    } else {
+        let delta = req
+            .query::<f64>("adjust")
+            .ok_or_else(|| Error::BadArgument("No delay provided".to_string()))?;
+        tracing::debug!("[API: MPV] Adjusting subtitle delay by {}", delta);
+        clients::mpv::adjust_subtitle_delay(delta).await?;
+    }
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_subtitle_scale_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let scale = req
+        .query::<f64>("scale")
+        .ok_or_else(|| Error::BadArgument("No scale provided".to_string()))?;
+    tracing::debug!("[API: MPV] Setting subtitle scale to {}", scale);
+
+    clients::mpv::set_subtitle_scale(scale).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_subtitle_position_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let position = req
+        .query::<f64>("position")
+        .ok_or_else(|| Error::BadArgument("No position provided".to_string()))?;
+    tracing::debug!("[API: MPV] Setting subtitle position to {}", position);
+
+    clients::mpv::set_subtitle_position(position).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_subtitle_visibility_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Toggling subtitle visibility");
+    clients::mpv::toggle_subtitle_visibility().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_set_audio_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let id = req
+        .query::<i64>("id")
+        .ok_or_else(|| Error::BadArgument("No audio track ID provided".to_string()))?;
+    tracing::debug!("[API: MPV] Setting audio track to {}", id);
+
+    clients::mpv::set_audio_track(id).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_set_video_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let id = req
+        .query::<i64>("id")
+        .ok_or_else(|| Error::BadArgument("No video track ID provided".to_string()))?;
+    tracing::debug!("[API: MPV] Setting video track to {}", id);
+
+    clients::mpv::set_video_track(id).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
 }
 
 #[tauri::command]
-pub async fn handle_volume_up_cmd() {
+pub async fn handle_volume_up_cmd() -> Result<(), Error> {
     tracing::debug!("[Tauri] Volume up");
-    clients::mpv::volume_up().await;
+    clients::mpv::volume_up().await
+}
+
+#[handler]
+pub async fn handle_skip_backward_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let long = req.query::<bool>("long").unwrap_or(false);
+    tracing::debug!("[API: MPV] Skip backward (long: {})", long);
+
+    let skip = load_settings().skip.unwrap_or_default();
+    clients::mpv::skip_backward(&skip, long).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_skip_forward_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let long = req.query::<bool>("long").unwrap_or(false);
+    tracing::debug!("[API: MPV] Skip forward (long: {})", long);
+
+    let skip = load_settings().skip.unwrap_or_default();
+    clients::mpv::skip_forward(&skip, long).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+/// `mode` is one of `relative` (the default), `absolute`, `relative-percent`
+/// or `absolute-percent`.
+#[handler]
+pub async fn handle_seek_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let target = req
+        .query::<f64>("target")
+        .ok_or_else(|| Error::BadArgument("No target provided".to_string()))?;
+    let mode = match req.query::<String>("mode") {
+        Some(mode) => serde_json::from_value::<SeekMode>(json!(mode))
+            .map_err(|_| Error::BadArgument(format!("Unknown seek mode: {}", mode)))?,
+        None => SeekMode::Relative,
+    };
+    let exact = req.query::<bool>("exact");
+    tracing::debug!(
+        "[API: MPV] Seeking to {} ({:?}, exact: {:?})",
+        target,
+        mode,
+        exact
+    );
+
+    clients::mpv::seek_with(target, mode, exact).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
 }
 
 #[handler]
-pub async fn handle_skip_backward_api(_req: &mut Request, res: &mut Response, _depot: &mut Depot) {
-    tracing::debug!("[API: MPV] Skip forward");
-    clients::mpv::skip_backward().await;
-    res.render(Text::Plain("ok"))
+pub async fn handle_speed_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    if let Some(speed) = req.query::<f64>("set") {
+        tracing::debug!("[API: MPV] Setting speed to {}", speed);
+        clients::mpv::set_speed(speed).await?;
+    } else {
+        let delta = req
+            .query::<f64>("adjust")
+            .ok_or_else(|| Error::BadArgument("No speed provided".to_string()))?;
+        tracing::debug!("[API: MPV] Adjusting speed by {}", delta);
+        clients::mpv::adjust_speed(delta).await?;
+    }
+    res.render(Text::Plain("ok"));
+    Ok(())
 }
 
 #[handler]
-pub async fn handle_skip_forward_api(_req: &mut Request, res: &mut Response, _depot: &mut Depot) {
-    tracing::debug!("[API: MPV] Skip forward");
-    clients::mpv::skip_forward().await;
-    res.render(Text::Plain("ok"))
+pub async fn handle_speed_presets_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Get speed presets");
+    res.render(Json(clients::mpv::SPEED_PRESETS));
+    Ok(())
 }
 
 #[handler]
-pub async fn handle_set_volume_api(req: &mut Request, res: &mut Response, _depot: &mut Depot) {
-    let volume = req.query::<i16>("volume").expect("No volume provided");
+pub async fn handle_speed_cycle_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Cycling speed presets");
+    clients::mpv::cycle_speed().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_pitch_correction_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Toggling pitch correction");
+    clients::mpv::toggle_pitch_correction().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+/// Leaving out `a` or `b` unsets that point.
+#[handler]
+pub async fn handle_ab_loop_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let a = req.query::<f64>("a");
+    let b = req.query::<f64>("b");
+    tracing::debug!("[API: MPV] Setting A-B loop to {:?} - {:?}", a, b);
+
+    clients::mpv::set_ab_loop(a, b).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_ab_loop_clear_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Clearing A-B loop");
+    clients::mpv::clear_ab_loop().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_ab_loop_cycle_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Cycling A-B loop");
+    clients::mpv::cycle_ab_loop().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_chapter_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let index = req
+        .query::<i64>("index")
+        .ok_or_else(|| Error::BadArgument("No chapter index provided".to_string()))?;
+    tracing::debug!("[API: MPV] Jumping to chapter {}", index);
+
+    clients::mpv::set_chapter(index).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_chapter_next_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Next chapter");
+    clients::mpv::chapter_next().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_chapter_previous_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Previous chapter");
+    clients::mpv::chapter_previous().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+/// `mode` is one of `replace` (the default), `append`, `append-play` or
+/// `insert-next`.
+#[handler]
+pub async fn handle_loadfile_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let target = req
+        .query::<String>("target")
+        .ok_or_else(|| Error::BadArgument("No path or URL provided".to_string()))?;
+    let mode = match req.query::<String>("mode") {
+        Some(mode) => serde_json::from_value::<LoadMode>(json!(mode))
+            .map_err(|_| Error::BadArgument(format!("Unknown load mode: {}", mode)))?,
+        None => LoadMode::Replace,
+    };
+    tracing::debug!("[API: MPV] Loading {} ({:?})", target, mode);
+
+    clients::mpv::loadfile(&target, mode).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+/// Takes a screenshot and sends the image back. `subtitles` (default true)
+/// includes them, `format` is `jpg` (the default) or `png`, and `max_width`
+/// scales it down. With `save=false` it's not kept in the gallery, for live
+/// previews.
+#[handler]
+pub async fn handle_screenshot_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let subtitles = req.query::<bool>("subtitles").unwrap_or(true);
+    let format = match req.query::<String>("format") {
+        Some(format) => serde_json::from_value::<ImageFormat>(json!(format))
+            .map_err(|_| Error::BadArgument(format!("Unknown image format: {}", format)))?,
+        None => ImageFormat::Jpg,
+    };
+    let max_width = req.query::<u32>("max_width");
+    let save = req.query::<bool>("save").unwrap_or(true);
+    tracing::debug!(
+        "[API: MPV] Taking screenshot (subtitles: {}, {:?}, max width: {:?}, save: {})",
+        subtitles,
+        format,
+        max_width,
+        save
+    );
+
+    let path = clients::mpv::take_screenshot(subtitles, format).await?;
+    let (bytes, content_type) =
+        clients::mpv::read_screenshot(path.clone(), max_width, save).await?;
+    // Lets the caller find it in the gallery afterwards.
+    let name = path.file_name().and_then(|name| name.to_str());
+    if let Some(Ok(name)) = name.filter(|_| save).map(HeaderValue::from_str) {
+        res.headers_mut().insert("x-screenshot-name", name);
+    }
+    render_image(res, bytes, content_type);
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_screenshots_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Listing screenshots");
+    res.render(Json(clients::mpv::list_screenshots()));
+    Ok(())
+}
+
+/// A screenshot from the gallery, `max_width` scales it down for thumbnails.
+#[handler]
+pub async fn handle_get_screenshot_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let name = req.param::<String>("name").unwrap_or_default();
+    let max_width = req.query::<u32>("max_width");
+    tracing::debug!(
+        "[API: MPV] Get screenshot {} (max width: {:?})",
+        name,
+        max_width
+    );
+
+    let path = clients::mpv::screenshot_path(&name)?;
+    let (bytes, content_type) = clients::mpv::read_screenshot(path, max_width, true).await?;
+    render_image(res, bytes, content_type);
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_delete_screenshot_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let name = req.param::<String>("name").unwrap_or_default();
+    tracing::debug!("[API: MPV] Deleting screenshot {}", name);
+
+    clients::mpv::delete_screenshot(&name)?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+fn render_image(res: &mut Response, bytes: Vec<u8>, content_type: &'static str) {
+    res.headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    res.body(ResBody::Once(bytes.into()));
+}
+
+#[handler]
+pub async fn handle_playlist_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Get playlist");
+    let playlist = clients::mpv::get_playlist().await?;
+    res.render(Json(playlist));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_playlist_play_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let index = req
+        .query::<i64>("index")
+        .ok_or_else(|| Error::BadArgument("No playlist index provided".to_string()))?;
+    tracing::debug!("[API: MPV] Playing playlist entry {}", index);
+
+    clients::mpv::playlist_play(index).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_playlist_next_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Next playlist entry");
+    clients::mpv::playlist_next().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_playlist_previous_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Previous playlist entry");
+    clients::mpv::playlist_previous().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_playlist_remove_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let index = req
+        .query::<i64>("index")
+        .ok_or_else(|| Error::BadArgument("No playlist index provided".to_string()))?;
+    tracing::debug!("[API: MPV] Removing playlist entry {}", index);
+
+    clients::mpv::playlist_remove(index).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_playlist_move_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let from = req
+        .query::<i64>("from")
+        .ok_or_else(|| Error::BadArgument("No from index provided".to_string()))?;
+    let to = req
+        .query::<i64>("to")
+        .ok_or_else(|| Error::BadArgument("No to index provided".to_string()))?;
+    tracing::debug!("[API: MPV] Moving playlist entry {} to {}", from, to);
+
+    clients::mpv::playlist_move(from, to).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_playlist_shuffle_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Shuffling playlist");
+    clients::mpv::playlist_shuffle().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_playlist_unshuffle_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Unshuffling playlist");
+    clients::mpv::playlist_unshuffle().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_playlist_clear_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Clearing playlist");
+    clients::mpv::playlist_clear().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_loop_file_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Toggling file loop");
+    clients::mpv::toggle_loop_file().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_loop_playlist_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Toggling playlist loop");
+    clients::mpv::toggle_loop_playlist().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_set_volume_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let volume = req
+        .query::<i16>("volume")
+        .ok_or_else(|| Error::BadArgument("No volume provided".to_string()))?;
     tracing::debug!("[API: MPV] Setting volume to {}", volume);
 
-    clients::mpv::set_volume(volume).await;
-    res.render(Text::Plain("ok"))
+    clients::mpv::set_volume(volume).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
 }
 
 #[handler]
-pub async fn handle_volume_up_api(_req: &mut Request, res: &mut Response, _depot: &mut Depot) {
+pub async fn handle_volume_up_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
     tracing::debug!("[API: MPV] Volume up");
-    clients::mpv::volume_up().await;
-    res.render(Text::Plain("ok"))
+    clients::mpv::volume_up().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_mute_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Toggling mute");
+    clients::mpv::toggle_mute().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_volume_limits_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Get volume limits");
+    res.render(Json(clients::mpv::volume_limits()));
+    Ok(())
+}
+
+/// Leaving out `enabled` toggles night mode.
+#[handler]
+pub async fn handle_night_mode_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let enabled = req
+        .query::<bool>("enabled")
+        .unwrap_or_else(|| !clients::mpv::volume_limits().night_mode);
+    tracing::debug!("[API: MPV] Setting night mode to {}", enabled);
+
+    clients::mpv::set_night_mode(enabled).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_normalize_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Toggling normalization");
+    clients::mpv::toggle_normalization().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
 }
 
 #[tauri::command]
-pub async fn handle_volume_down_cmd() {
+pub async fn handle_volume_down_cmd() -> Result<(), Error> {
     tracing::debug!("[Tauri] Volume down");
-    clients::mpv::volume_down().await;
+    clients::mpv::volume_down().await
 }
 
 #[handler]
-pub async fn handle_volume_down_api(_req: &mut Request, res: &mut Response, _depot: &mut Depot) {
+pub async fn handle_volume_down_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
     tracing::debug!("[API: MPV] Volume down");
-    clients::mpv::volume_down().await;
-    res.render(Text::Plain("ok"))
+    clients::mpv::volume_down().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_start_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Starting mpv");
+    clients::mpv::start().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_quit_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: MPV] Quitting mpv");
+    clients::mpv::quit().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
 }
 
 #[handler]
-pub async fn handle_status_api(_req: &mut Request, res: &mut Response, _depot: &mut Depot) {
+pub async fn handle_status_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
     tracing::debug!("[API: MPV] Get MPV status");
-    let status = clients::mpv::get_status().await.unwrap();
-    let response = serde_json::to_string(&status).expect("Failed to serialize status");
-    res.render(Text::Json(response))
+    let status = clients::mpv::get_status().await?;
+    res.render(Json(status));
+    Ok(())
 }
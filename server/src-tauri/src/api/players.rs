@@ -0,0 +1,233 @@
+use std::sync::Arc;
+
+use salvo::{
+    handler,
+    writing::{Json, Text},
+    Depot, Request, Response,
+};
+
+use crate::clients::{self, Error, MediaPlayer};
+
+fn player(req: &mut Request) -> Result<Arc<dyn MediaPlayer>, Error> {
+    let id = req
+        .param::<String>("id")
+        .ok_or_else(|| Error::BadArgument("No player ID provided".to_string()))?;
+    clients::get_player(&id)
+}
+
+#[handler]
+pub async fn handle_list_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: Players] Listing players");
+    res.render(Json(clients::configured_players()));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_status_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: Players] Get status");
+    let status = player(req)?.status().await?;
+    res.render(Json(status));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_pause_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: Players] Toggling pause");
+    player(req)?.toggle_pause().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_seek_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let player = player(req)?;
+    if let Some(position) = req.query::<f64>("position") {
+        tracing::debug!("[API: Players] Seeking to {}", position);
+        player.seek_to(position).await?;
+    } else {
+        let offset = req
+            .query::<f64>("offset")
+            .ok_or_else(|| Error::BadArgument("No offset or position provided".to_string()))?;
+        tracing::debug!("[API: Players] Seeking by {}", offset);
+        player.seek(offset).await?;
+    }
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_set_volume_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let volume = req
+        .query::<i16>("volume")
+        .ok_or_else(|| Error::BadArgument("No volume provided".to_string()))?;
+    tracing::debug!("[API: Players] Setting volume to {}", volume);
+
+    player(req)?.set_volume(volume).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_volume_up_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: Players] Volume up");
+    player(req)?.volume_up().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_volume_down_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: Players] Volume down");
+    player(req)?.volume_down().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_set_subtitle_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let id = req
+        .query::<i64>("id")
+        .ok_or_else(|| Error::BadArgument("No subtitle ID provided".to_string()))?;
+    tracing::debug!("[API: Players] Setting subtitle to {}", id);
+
+    player(req)?.set_subtitle(id).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_subtitle_off_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: Players] Disabling subtitles");
+    player(req)?.disable_subtitles().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_set_audio_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let id = req
+        .query::<i64>("id")
+        .ok_or_else(|| Error::BadArgument("No audio track ID provided".to_string()))?;
+    tracing::debug!("[API: Players] Setting audio track to {}", id);
+
+    player(req)?.set_audio_track(id).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_set_video_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    let id = req
+        .query::<i64>("id")
+        .ok_or_else(|| Error::BadArgument("No video track ID provided".to_string()))?;
+    tracing::debug!("[API: Players] Setting video track to {}", id);
+
+    player(req)?.set_video_track(id).await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_playlist_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: Players] Get playlist");
+    let playlist = player(req)?.playlist().await?;
+    res.render(Json(playlist));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_next_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: Players] Next");
+    player(req)?.playlist_next().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_previous_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: Players] Previous");
+    player(req)?.playlist_previous().await?;
+    res.render(Text::Plain("ok"));
+    Ok(())
+}
+
+#[handler]
+pub async fn handle_wait_api(
+    req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: Players] Waiting for changes");
+    let changed = player(req)?.wait_for_change().await?;
+    res.render(Json(changed));
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[handler]
+pub async fn handle_mpris_list_api(
+    _req: &mut Request,
+    res: &mut Response,
+    _depot: &mut Depot,
+) -> Result<(), Error> {
+    tracing::debug!("[API: Players] Listing MPRIS players");
+    let players = clients::mpris::list_players().await?;
+    res.render(Json(players));
+    Ok(())
+}
@@ -0,0 +1,379 @@
+use salvo::http::StatusError;
+use salvo::websocket::{Message, WebSocket, WebSocketUpgrade};
+use salvo::{handler, Request, Response};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::clients::{
+    self,
+    mpv::{Error, LoadMode, SeekMode},
+};
+use crate::settings::load_settings;
+
+/// Commands accepted over the socket, mirroring the `/mpv/*` GET routes.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+enum Command {
+    Status,
+    Start,
+    Quit,
+    Pause,
+    SetVolume {
+        volume: i16,
+    },
+    VolumeUp,
+    VolumeDown,
+    Mute,
+    NightMode {
+        enabled: Option<bool>,
+    },
+    Normalize,
+    SkipBackward {
+        #[serde(default)]
+        long: bool,
+    },
+    SkipForward {
+        #[serde(default)]
+        long: bool,
+    },
+    Seek {
+        target: f64,
+        #[serde(default = "relative")]
+        mode: SeekMode,
+        exact: Option<bool>,
+    },
+    Speed {
+        set: Option<f64>,
+        adjust: Option<f64>,
+    },
+    SpeedCycle,
+    PitchCorrection,
+    AbLoop {
+        a: Option<f64>,
+        b: Option<f64>,
+    },
+    AbLoopClear,
+    AbLoopCycle,
+    Chapter {
+        index: i64,
+    },
+    ChapterNext,
+    ChapterPrevious,
+    Loadfile {
+        target: String,
+        #[serde(default = "replace")]
+        mode: LoadMode,
+    },
+    PlaylistPlay {
+        index: i64,
+    },
+    PlaylistNext,
+    PlaylistPrevious,
+    PlaylistRemove {
+        index: i64,
+    },
+    PlaylistMove {
+        from: i64,
+        to: i64,
+    },
+    PlaylistShuffle,
+    PlaylistUnshuffle,
+    PlaylistClear,
+    LoopFile,
+    LoopPlaylist,
+    Subtitle {
+        id: i64,
+    },
+    SubtitleOff,
+    SecondarySubtitle {
+        id: Option<i64>,
+    },
+    SubtitleDelay {
+        set: Option<f64>,
+        adjust: Option<f64>,
+    },
+    SubtitleScale {
+        scale: f64,
+    },
+    SubtitlePosition {
+        position: f64,
+    },
+    SubtitleVisibility,
+    Audio {
+        id: i64,
+    },
+    Video {
+        id: i64,
+    },
+}
+
+fn relative() -> SeekMode {
+    SeekMode::Relative
+}
+
+fn replace() -> LoadMode {
+    LoadMode::Replace
+}
+
+#[handler]
+pub async fn handle_ws(req: &mut Request, res: &mut Response) -> Result<(), StatusError> {
+    tracing::debug!("[API: WS] Client connected");
+    // The session runs in its own task, carry the instance over.
+    let instance = clients::mpv::current_instance();
+    WebSocketUpgrade::new()
+        .upgrade(req, res, move |ws| {
+            clients::mpv::scope(instance, session(ws))
+        })
+        .await
+}
+
+async fn session(mut ws: WebSocket) {
+    let mut status = clients::mpv::watch_status();
+    let mut last = snapshot(&status.borrow_and_update());
+
+    if send(&mut ws, json!({ "type": "status", "status": last }))
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            changed = status.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+
+                let current = snapshot(&status.borrow_and_update());
+                let message = match (last.as_mut(), current) {
+                    (Some(last), Some(current)) => {
+                        let changes = diff(last, current);
+                        if changes.is_empty() {
+                            continue;
+                        }
+                        json!({ "type": "changed", "changes": changes })
+                    }
+                    (None, None) => continue,
+                    // mpv started or went away, resend everything.
+                    (_, current) => {
+                        last = current;
+                        json!({ "type": "status", "status": last })
+                    }
+                };
+
+                if send(&mut ws, message).await.is_err() {
+                    return;
+                }
+            }
+            message = ws.recv() => {
+                let Some(Ok(message)) = message else {
+                    tracing::debug!("[API: WS] Client disconnected");
+                    return;
+                };
+                if message.is_close() {
+                    tracing::debug!("[API: WS] Client disconnected");
+                    return;
+                }
+                let Ok(text) = message.to_str() else {
+                    continue;
+                };
+
+                let reply = match serde_json::from_str::<Command>(text) {
+                    Ok(command) => run(command).await,
+                    Err(e) => json!({ "type": "error", "error": "bad-argument", "message": e.to_string() }),
+                };
+
+                if send(&mut ws, reply).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn run(command: Command) -> Value {
+    tracing::debug!("[API: WS] {:?}", command);
+    let result = match command {
+        Command::Status => {
+            let status = clients::mpv::get_status().await.ok();
+            return json!({ "type": "status", "status": status });
+        }
+        Command::Start => clients::mpv::start().await,
+        Command::Quit => clients::mpv::quit().await,
+        Command::Pause => clients::mpv::toggle_pause().await,
+        Command::SetVolume { volume } => clients::mpv::set_volume(volume).await,
+        Command::VolumeUp => clients::mpv::volume_up().await,
+        Command::VolumeDown => clients::mpv::volume_down().await,
+        Command::Mute => clients::mpv::toggle_mute().await,
+        Command::NightMode { enabled } => {
+            let enabled = enabled.unwrap_or_else(|| !clients::mpv::volume_limits().night_mode);
+            clients::mpv::set_night_mode(enabled).await
+        }
+        Command::Normalize => clients::mpv::toggle_normalization().await,
+        Command::SkipBackward { long } => {
+            let skip = load_settings().skip.unwrap_or_default();
+            clients::mpv::skip_backward(&skip, long).await
+        }
+        Command::SkipForward { long } => {
+            let skip = load_settings().skip.unwrap_or_default();
+            clients::mpv::skip_forward(&skip, long).await
+        }
+        Command::Seek {
+            target,
+            mode,
+            exact,
+        } => clients::mpv::seek_with(target, mode, exact).await,
+        Command::Speed {
+            set: Some(speed), ..
+        } => clients::mpv::set_speed(speed).await,
+        Command::Speed {
+            adjust: Some(delta),
+            ..
+        } => clients::mpv::adjust_speed(delta).await,
+        Command::Speed { .. } => Err(Error::BadArgument("No speed provided".to_string())),
+        Command::SpeedCycle => clients::mpv::cycle_speed().await,
+        Command::PitchCorrection => clients::mpv::toggle_pitch_correction().await,
+        Command::AbLoop { a, b } => clients::mpv::set_ab_loop(a, b).await,
+        Command::AbLoopClear => clients::mpv::clear_ab_loop().await,
+        Command::AbLoopCycle => clients::mpv::cycle_ab_loop().await,
+        Command::Chapter { index } => clients::mpv::set_chapter(index).await,
+        Command::ChapterNext => clients::mpv::chapter_next().await,
+        Command::ChapterPrevious => clients::mpv::chapter_previous().await,
+        Command::Loadfile { target, mode } => clients::mpv::loadfile(&target, mode).await,
+        Command::PlaylistPlay { index } => clients::mpv::playlist_play(index).await,
+        Command::PlaylistNext => clients::mpv::playlist_next().await,
+        Command::PlaylistPrevious => clients::mpv::playlist_previous().await,
+        Command::PlaylistRemove { index } => clients::mpv::playlist_remove(index).await,
+        Command::PlaylistMove { from, to } => clients::mpv::playlist_move(from, to).await,
+        Command::PlaylistShuffle => clients::mpv::playlist_shuffle().await,
+        Command::PlaylistUnshuffle => clients::mpv::playlist_unshuffle().await,
+        Command::PlaylistClear => clients::mpv::playlist_clear().await,
+        Command::LoopFile => clients::mpv::toggle_loop_file().await,
+        Command::LoopPlaylist => clients::mpv::toggle_loop_playlist().await,
+        Command::Subtitle { id } => clients::mpv::set_subtitle(id).await,
+        Command::SubtitleOff => clients::mpv::disable_subtitles().await,
+        Command::SecondarySubtitle { id } => clients::mpv::set_secondary_subtitle(id).await,
+        Command::SubtitleDelay {
+            set: Some(delay), ..
+        } => clients::mpv::set_subtitle_delay(delay).await,
+        Command::SubtitleDelay {
+            adjust: Some(delta),
+            ..
+        } => clients::mpv::adjust_subtitle_delay(delta).await,
+        Command::SubtitleDelay { .. } => Err(Error::BadArgument("No delay provided".to_string())),
+        Command::SubtitleScale { scale } => clients::mpv::set_subtitle_scale(scale).await,
+        Command::SubtitlePosition { position } => {
+            clients::mpv::set_subtitle_position(position).await
+        }
+        Command::SubtitleVisibility => clients::mpv::toggle_subtitle_visibility().await,
+        Command::Audio { id } => clients::mpv::set_audio_track(id).await,
+        Command::Video { id } => clients::mpv::set_video_track(id).await,
+    };
+
+    match result {
+        Ok(()) => json!({ "type": "ok" }),
+        Err(e) => json!({ "type": "error", "error": e.kind(), "message": e.to_string() }),
+    }
+}
+
+async fn send(ws: &mut WebSocket, message: Value) -> Result<(), salvo::Error> {
+    ws.send(Message::text(message.to_string())).await
+}
+
+fn snapshot(status: &Option<clients::mpv::Status>) -> Option<Map<String, Value>> {
+    match serde_json::to_value(status.as_ref()?) {
+        Ok(Value::Object(fields)) => Some(fields),
+        _ => None,
+    }
+}
+
+/// Fields of `current` that differ from `last`, updating `last` as it goes. The
+/// position only counts as changed once it has moved by a second, so playback
+/// produces one tick per second instead of one per frame.
+fn diff(last: &mut Map<String, Value>, current: Map<String, Value>) -> Map<String, Value> {
+    let mut changes = Map::new();
+    for (key, value) in current {
+        if last.get(&key) == Some(&value) {
+            continue;
+        }
+
+        if key == "position" {
+            let previous = last.get(&key).and_then(Value::as_f64).unwrap_or_default();
+            let position = value.as_f64().unwrap_or_default();
+            if (position - previous).abs() < 1.0 {
+                continue;
+            }
+        }
+
+        last.insert(key.clone(), value.clone());
+        changes.insert(key, value);
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(fields) => fields,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn reports_only_changed_fields() {
+        let mut last = fields(json!({ "paused": true, "volume": 50, "title": "a" }));
+
+        let changes = diff(
+            &mut last,
+            fields(json!({ "paused": false, "volume": 50, "title": "b" })),
+        );
+
+        assert_eq!(
+            Value::Object(changes),
+            json!({ "paused": false, "title": "b" })
+        );
+        assert_eq!(
+            Value::Object(last),
+            json!({ "paused": false, "volume": 50, "title": "b" })
+        );
+    }
+
+    #[test]
+    fn unchanged_status_has_no_changes() {
+        let status = json!({ "paused": true, "subtitles": [{ "id": 1 }] });
+        let mut last = fields(status.clone());
+
+        assert!(diff(&mut last, fields(status)).is_empty());
+    }
+
+    #[test]
+    fn position_ticks_once_per_second() {
+        let mut last = fields(json!({ "position": 10.0 }));
+        let mut tick = |position: f64| diff(&mut last, fields(json!({ "position": position })));
+
+        // Small steps add up against the last position sent, not the last one seen.
+        assert!(tick(10.4).is_empty());
+        assert!(tick(10.9).is_empty());
+        assert_eq!(Value::Object(tick(11.0)), json!({ "position": 11.0 }));
+        assert!(tick(11.5).is_empty());
+        // Seeking back counts the same.
+        assert_eq!(Value::Object(tick(3.0)), json!({ "position": 3.0 }));
+    }
+
+    #[test]
+    fn new_fields_are_changes() {
+        let mut last = fields(json!({ "paused": true }));
+
+        let changes = diff(&mut last, fields(json!({ "paused": true, "chapter": 2 })));
+        assert_eq!(Value::Object(changes), json!({ "chapter": 2 }));
+
+        // A missing position counts as zero.
+        let changes = diff(&mut last, fields(json!({ "position": 0.5 })));
+        assert!(changes.is_empty());
+    }
+}
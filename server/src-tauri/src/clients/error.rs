@@ -0,0 +1,54 @@
+use std::fmt;
+
+use serde::{Serialize, Serializer};
+
+/// Shared by every backend, so messages don't name a particular player.
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// Nothing is listening where the player should be, or it went away mid-request.
+    NotRunning,
+    /// The player accepted the command but never answered.
+    Timeout,
+    /// The player answered with something we didn't expect.
+    Protocol(String),
+    /// The property exists but has no value right now, e.g. `time-pos` with nothing playing.
+    PropertyUnavailable(String),
+    /// The caller asked for something the player (or we) can't do.
+    BadArgument(String),
+}
+
+impl Error {
+    /// Stable identifier for API clients to match on.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::NotRunning => "not-running",
+            Error::Timeout => "timeout",
+            Error::Protocol(_) => "protocol",
+            Error::PropertyUnavailable(_) => "property-unavailable",
+            Error::BadArgument(_) => "bad-argument",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotRunning => write!(f, "player is not running"),
+            Error::Timeout => write!(f, "player did not respond in time"),
+            Error::Protocol(message) => write!(f, "unexpected reply from player: {}", message),
+            Error::PropertyUnavailable(property) => {
+                write!(f, "property unavailable: {}", property)
+            }
+            Error::BadArgument(message) => write!(f, "bad argument: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Tauri commands hand errors to the frontend as their message.
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
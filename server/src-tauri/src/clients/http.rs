@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+
+use crate::clients::Error;
+
+lazy_static! {
+    /// Shared by the backends reached over HTTP.
+    pub static ref CLIENT: reqwest::Client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+}
+
+pub fn map_error(e: reqwest::Error) -> Error {
+    if e.is_timeout() {
+        Error::Timeout
+    } else if e.is_connect() {
+        Error::NotRunning
+    } else {
+        Error::Protocol(e.to_string())
+    }
+}
+
+/// Minimal HTTP server standing in for a player's web interface.
+#[cfg(test)]
+pub mod stub {
+    use serde_json::Value;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    /// A request as the stub saw it.
+    #[derive(Debug)]
+    pub struct Request {
+        /// Like `GET /requests/status.json?command=pl_pause`.
+        pub line: String,
+        pub body: String,
+    }
+
+    /// Answers one request per reply, in order, each on its own connection.
+    /// The handle resolves to the requests once every reply is sent.
+    pub async fn serve(replies: Vec<Value>) -> (u16, JoinHandle<Vec<Request>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let handle = tokio::spawn(async move {
+            let mut requests = vec![];
+            for reply in replies {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut stream = BufReader::new(stream);
+
+                let mut line = String::new();
+                stream.read_line(&mut line).await.unwrap();
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    stream.read_line(&mut header).await.unwrap();
+                    let header = header.trim_end();
+                    if header.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(": ") {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                stream.read_exact(&mut body).await.unwrap();
+
+                let reply = reply.to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    reply.len(),
+                    reply
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.unwrap();
+
+                requests.push(Request {
+                    line: line.trim_end().trim_end_matches(" HTTP/1.1").to_string(),
+                    body: String::from_utf8(body).unwrap(),
+                });
+            }
+            requests
+        });
+
+        (port, handle)
+    }
+}
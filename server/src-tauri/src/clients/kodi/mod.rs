@@ -0,0 +1,541 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::clients::http::{map_error, CLIENT};
+use crate::clients::MediaPlayer;
+use crate::clients::{Error, PlaylistEntry, Status, Subtitle, Track};
+use crate::settings::KodiSettings;
+
+/// Kodi driven over its JSON-RPC HTTP API.
+pub struct Kodi {
+    settings: KodiSettings,
+}
+
+/// Kodi reports times as `{ hours, minutes, seconds, milliseconds }`.
+fn seconds(time: &Value) -> f64 {
+    time["hours"].as_f64().unwrap_or(0.0) * 3600.0
+        + time["minutes"].as_f64().unwrap_or(0.0) * 60.0
+        + time["seconds"].as_f64().unwrap_or(0.0)
+        + time["milliseconds"].as_f64().unwrap_or(0.0) / 1000.0
+}
+
+fn time(seconds: f64) -> Value {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    json!({
+        "hours": millis / 3_600_000,
+        "minutes": millis / 60_000 % 60,
+        "seconds": millis / 1000 % 60,
+        "milliseconds": millis % 1000,
+    })
+}
+
+fn subtitle_title(subtitle: &Value) -> String {
+    let lang = subtitle["language"].as_str().unwrap_or("null");
+    match subtitle["name"].as_str() {
+        Some(name) if !name.is_empty() => format!("{} - {}", lang, name),
+        _ => lang.to_string(),
+    }
+}
+
+fn streams(streams: &Value) -> Vec<Track> {
+    streams
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|stream| {
+            let text = |key: &str| {
+                stream[key]
+                    .as_str()
+                    .filter(|value| !value.is_empty())
+                    .map(str::to_string)
+            };
+            Some(Track {
+                id: stream["index"].as_i64()?,
+                lang: text("language"),
+                title: text("name"),
+                codec: text("codec"),
+                channels: stream["channels"].as_i64(),
+            })
+        })
+        .collect()
+}
+
+impl Kodi {
+    pub fn new(settings: KodiSettings) -> Self {
+        Kodi { settings }
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value, Error> {
+        let url = format!(
+            "http://{}:{}/jsonrpc",
+            self.settings.host, self.settings.port
+        );
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+
+        let mut request = CLIENT.post(url).json(&body);
+        if let Some(username) = &self.settings.username {
+            request = request.basic_auth(username, self.settings.password.as_ref());
+        }
+
+        let response = request.send().await.map_err(map_error)?;
+        if !response.status().is_success() {
+            return Err(Error::Protocol(format!(
+                "Kodi answered {}",
+                response.status()
+            )));
+        }
+
+        let mut response = response.json::<Value>().await.map_err(map_error)?;
+        if let Some(error) = response.get("error") {
+            tracing::debug!("Kodi rejected {}: {}", method, error);
+            let message = error["message"].as_str().unwrap_or_default().to_string();
+            // -32602 is JSON-RPC's "Invalid params".
+            return Err(match error["code"].as_i64() {
+                Some(-32602) => Error::BadArgument(message),
+                _ => Error::Protocol(message),
+            });
+        }
+
+        Ok(response["result"].take())
+    }
+
+    /// Id of the player currently playing something, preferring video.
+    async fn player_id(&self) -> Result<i64, Error> {
+        let players = self.call("Player.GetActivePlayers", json!({})).await?;
+        let players = players.as_array().cloned().unwrap_or_default();
+
+        players
+            .iter()
+            .find(|player| player["type"] == "video")
+            .or_else(|| players.first())
+            .and_then(|player| player["playerid"].as_i64())
+            .ok_or_else(|| Error::PropertyUnavailable("active player".to_string()))
+    }
+
+    async fn volume(&self) -> Result<i64, Error> {
+        let properties = self
+            .call(
+                "Application.GetProperties",
+                json!({ "properties": ["volume"] }),
+            )
+            .await?;
+        properties["volume"]
+            .as_i64()
+            .ok_or_else(|| Error::PropertyUnavailable("volume".to_string()))
+    }
+}
+
+#[async_trait]
+impl MediaPlayer for Kodi {
+    async fn toggle_pause(&self) -> Result<(), Error> {
+        let playerid = self.player_id().await?;
+        self.call("Player.PlayPause", json!({ "playerid": playerid }))
+            .await?;
+        Ok(())
+    }
+
+    async fn seek(&self, offset: f64) -> Result<(), Error> {
+        let playerid = self.player_id().await?;
+        let value = json!({ "seconds": offset.round() as i64 });
+        self.call(
+            "Player.Seek",
+            json!({ "playerid": playerid, "value": value }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn seek_to(&self, position: f64) -> Result<(), Error> {
+        let playerid = self.player_id().await?;
+        let value = json!({ "time": time(position) });
+        self.call(
+            "Player.Seek",
+            json!({ "playerid": playerid, "value": value }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn set_volume(&self, volume: i16) -> Result<(), Error> {
+        self.call(
+            "Application.SetVolume",
+            json!({ "volume": volume.clamp(0, 100) }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn volume_up(&self) -> Result<(), Error> {
+        self.call("Application.SetVolume", json!({ "volume": "increment" }))
+            .await?;
+        Ok(())
+    }
+
+    async fn volume_down(&self) -> Result<(), Error> {
+        self.call("Application.SetVolume", json!({ "volume": "decrement" }))
+            .await?;
+        Ok(())
+    }
+
+    async fn set_subtitle(&self, id: i64) -> Result<(), Error> {
+        let playerid = self.player_id().await?;
+        self.call(
+            "Player.SetSubtitle",
+            json!({ "playerid": playerid, "subtitle": id, "enable": true }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn disable_subtitles(&self) -> Result<(), Error> {
+        let playerid = self.player_id().await?;
+        self.call(
+            "Player.SetSubtitle",
+            json!({ "playerid": playerid, "subtitle": "off" }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn set_audio_track(&self, id: i64) -> Result<(), Error> {
+        let playerid = self.player_id().await?;
+        self.call(
+            "Player.SetAudioStream",
+            json!({ "playerid": playerid, "stream": id }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn set_video_track(&self, id: i64) -> Result<(), Error> {
+        let playerid = self.player_id().await?;
+        self.call(
+            "Player.SetVideoStream",
+            json!({ "playerid": playerid, "stream": id }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn status(&self) -> Result<Status, Error> {
+        let volume = self.volume().await?;
+        let playerid = match self.player_id().await {
+            Ok(playerid) => playerid,
+            // Kodi is up but idle.
+            Err(Error::PropertyUnavailable(_)) => {
+                return Ok(Status {
+                    paused: true,
+                    volume: volume as f64,
+                    position: 0.0,
+                    length: 0.0,
+                    title: Value::Null.to_string(),
+                    file: Value::Null.to_string(),
+                    subtitle: None,
+                    subtitles: vec![],
+                    ..Default::default()
+                });
+            }
+            Err(e) => return Err(e),
+        };
+
+        let properties = self
+            .call(
+                "Player.GetProperties",
+                json!({
+                    "playerid": playerid,
+                    "properties": [
+                        "speed",
+                        "time",
+                        "totaltime",
+                        "subtitles",
+                        "currentsubtitle",
+                        "subtitleenabled",
+                        "audiostreams",
+                        "currentaudiostream",
+                        "videostreams",
+                        "currentvideostream",
+                    ],
+                }),
+            )
+            .await?;
+        let item = self
+            .call(
+                "Player.GetItem",
+                json!({ "playerid": playerid, "properties": ["title", "file"] }),
+            )
+            .await?;
+        let item = &item["item"];
+
+        let title = match item["title"].as_str() {
+            Some(title) if !title.is_empty() => title,
+            _ => item["label"].as_str().unwrap_or_default(),
+        };
+
+        let subtitle = if properties["subtitleenabled"] == true {
+            properties["currentsubtitle"]["index"].as_i64()
+        } else {
+            None
+        };
+
+        let subtitles = properties["subtitles"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|subtitle| {
+                Some(Subtitle {
+                    id: subtitle["index"].as_i64()?,
+                    title: subtitle_title(subtitle),
+                    loaded: true,
+                })
+            })
+            .collect();
+
+        Ok(Status {
+            paused: properties["speed"].as_f64().unwrap_or(0.0) == 0.0,
+            volume: volume as f64,
+            position: seconds(&properties["time"]),
+            length: seconds(&properties["totaltime"]),
+            title: Value::from(title).to_string(),
+            file: item["file"].to_string(),
+            subtitle,
+            subtitles,
+            audio: properties["currentaudiostream"]["index"].as_i64(),
+            audio_tracks: streams(&properties["audiostreams"]),
+            video: properties["currentvideostream"]["index"].as_i64(),
+            video_tracks: streams(&properties["videostreams"]),
+            subtitle_visible: properties["subtitleenabled"] == true,
+            ..Default::default()
+        })
+    }
+
+    async fn playlist(&self) -> Result<Vec<PlaylistEntry>, Error> {
+        let playerid = self.player_id().await?;
+        let properties = self
+            .call(
+                "Player.GetProperties",
+                json!({ "playerid": playerid, "properties": ["playlistid", "position", "speed"] }),
+            )
+            .await?;
+        let items = self
+            .call(
+                "Playlist.GetItems",
+                json!({ "playlistid": properties["playlistid"], "properties": ["title", "file"] }),
+            )
+            .await?;
+
+        let position = properties["position"].as_i64();
+        let playing = properties["speed"].as_f64().unwrap_or(0.0) != 0.0;
+
+        Ok(items["items"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(index, item)| {
+                let current = position == Some(index as i64);
+                PlaylistEntry {
+                    filename: item["file"].as_str().unwrap_or_default().to_string(),
+                    title: item["title"]
+                        .as_str()
+                        .or(item["label"].as_str())
+                        .map(str::to_string),
+                    current,
+                    playing: current && playing,
+                }
+            })
+            .collect())
+    }
+
+    async fn playlist_next(&self) -> Result<(), Error> {
+        let playerid = self.player_id().await?;
+        self.call("Player.GoTo", json!({ "playerid": playerid, "to": "next" }))
+            .await?;
+        Ok(())
+    }
+
+    async fn playlist_previous(&self) -> Result<(), Error> {
+        let playerid = self.player_id().await?;
+        self.call(
+            "Player.GoTo",
+            json!({ "playerid": playerid, "to": "previous" }),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::http::stub;
+
+    fn kodi(port: u16) -> Kodi {
+        Kodi::new(KodiSettings {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: None,
+            password: None,
+        })
+    }
+
+    fn result(result: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": 1, "result": result })
+    }
+
+    fn methods(requests: &[stub::Request]) -> Vec<String> {
+        requests
+            .iter()
+            .map(|request| {
+                let body: Value = serde_json::from_str(&request.body).unwrap();
+                body["method"].as_str().unwrap().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn converts_times() {
+        let kodi_time = json!({ "hours": 1, "minutes": 2, "seconds": 3, "milliseconds": 500 });
+        assert_eq!(seconds(&kodi_time), 3723.5);
+        assert_eq!(time(3723.5), kodi_time);
+        assert_eq!(
+            time(-4.0),
+            json!({ "hours": 0, "minutes": 0, "seconds": 0, "milliseconds": 0 })
+        );
+    }
+
+    #[tokio::test]
+    async fn parses_status() {
+        let (port, requests) = stub::serve(vec![
+            result(json!({ "volume": 80 })),
+            result(json!([{ "playerid": 0, "type": "audio" }, { "playerid": 1, "type": "video" }])),
+            result(json!({
+                "speed": 1,
+                "time": { "hours": 0, "minutes": 1, "seconds": 30, "milliseconds": 0 },
+                "totaltime": { "hours": 0, "minutes": 45, "seconds": 0, "milliseconds": 0 },
+                "subtitles": [
+                    { "index": 0, "language": "eng", "name": "" },
+                    { "index": 1, "language": "ger", "name": "Forced" },
+                ],
+                "currentsubtitle": { "index": 1 },
+                "subtitleenabled": true,
+                "audiostreams": [{ "index": 0, "language": "eng", "name": "", "codec": "ac3", "channels": 6 }],
+                "currentaudiostream": { "index": 0 },
+                "videostreams": [],
+                "currentvideostream": null,
+            })),
+            result(json!({ "item": { "title": "", "label": "Episode", "file": "/tv/episode.mkv" } })),
+        ])
+        .await;
+
+        let status = kodi(port).status().await.unwrap();
+        assert!(!status.paused);
+        assert_eq!(status.volume, 80.0);
+        assert_eq!(status.position, 90.0);
+        assert_eq!(status.length, 2700.0);
+        assert_eq!(status.title, "\"Episode\"");
+        assert_eq!(status.file, "\"/tv/episode.mkv\"");
+        assert_eq!(status.subtitle, Some(1));
+        assert_eq!(status.subtitles[0].title, "eng");
+        assert_eq!(status.subtitles[1].title, "ger - Forced");
+        assert_eq!(status.audio, Some(0));
+        assert_eq!(status.audio_tracks[0].channels, Some(6));
+        assert_eq!(status.audio_tracks[0].title, None);
+
+        let requests = requests.await.unwrap();
+        assert_eq!(
+            methods(&requests),
+            [
+                "Application.GetProperties",
+                "Player.GetActivePlayers",
+                "Player.GetProperties",
+                "Player.GetItem",
+            ]
+        );
+        // The video player is preferred over the audio one.
+        let properties: Value = serde_json::from_str(&requests[2].body).unwrap();
+        assert_eq!(properties["params"]["playerid"], 1);
+    }
+
+    #[tokio::test]
+    async fn idle_status_without_player() {
+        let (port, _) = stub::serve(vec![result(json!({ "volume": 50 })), result(json!([]))]).await;
+
+        let status = kodi(port).status().await.unwrap();
+        assert!(status.paused);
+        assert_eq!(status.volume, 50.0);
+        assert_eq!(status.title, "null");
+    }
+
+    #[tokio::test]
+    async fn parses_playlist() {
+        let (port, _) = stub::serve(vec![
+            result(json!([{ "playerid": 0, "type": "audio" }])),
+            result(json!({ "playlistid": 0, "position": 1, "speed": 0 })),
+            result(json!({ "items": [
+                { "file": "/music/one.flac", "title": "One", "label": "one" },
+                { "file": "/music/two.flac", "label": "two" },
+            ] })),
+        ])
+        .await;
+
+        let entries = kodi(port).playlist().await.unwrap();
+        let entries: Vec<(&str, Option<&str>, bool, bool)> = entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.filename.as_str(),
+                    entry.title.as_deref(),
+                    entry.current,
+                    entry.playing,
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("/music/one.flac", Some("One"), false, false),
+                ("/music/two.flac", Some("two"), true, false),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn seeks_by_seconds() {
+        let players = result(json!([{ "playerid": 1, "type": "video" }]));
+        let (port, requests) = stub::serve(vec![
+            players.clone(),
+            result(json!({})),
+            players,
+            result(json!({})),
+        ])
+        .await;
+        let kodi = kodi(port);
+
+        kodi.seek(-10.4).await.unwrap();
+        kodi.seek_to(61.0).await.unwrap();
+
+        let requests = requests.await.unwrap();
+        let params = |index: usize| {
+            let body: Value = serde_json::from_str(&requests[index].body).unwrap();
+            body["params"]["value"].clone()
+        };
+        assert_eq!(params(1), json!({ "seconds": -10 }));
+        assert_eq!(
+            params(3),
+            json!({ "time": { "hours": 0, "minutes": 1, "seconds": 1, "milliseconds": 0 } })
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_params_is_bad_argument() {
+        let (port, _) = stub::serve(vec![json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": { "code": -32602, "message": "Invalid params." },
+        })])
+        .await;
+
+        let result = kodi(port).set_volume(50).await;
+        assert!(matches!(result, Err(Error::BadArgument(_))));
+    }
+}
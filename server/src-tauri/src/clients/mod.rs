@@ -1 +1,14 @@
+mod error;
+mod http;
+pub mod kodi;
+pub mod mpd;
+#[cfg(target_os = "linux")]
+pub mod mpris;
 pub mod mpv;
+mod player;
+pub mod vlc;
+
+pub use error::Error;
+pub use player::{
+    configured_players, get_player, Chapter, MediaPlayer, PlaylistEntry, Status, Subtitle, Track,
+};
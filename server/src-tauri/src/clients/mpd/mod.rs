@@ -0,0 +1,394 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+
+use crate::clients::MediaPlayer;
+use crate::clients::{Error, PlaylistEntry, Status};
+use crate::settings::MpdSettings;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const VOLUME_STEP: i64 = 2;
+
+/// Music Player Daemon, spoken to over its line based TCP protocol.
+pub struct Mpd {
+    settings: MpdSettings,
+}
+
+struct Session {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+}
+
+/// `key: value` pairs of a response, in the order MPD sent them.
+type Response = Vec<(String, String)>;
+
+fn get<'a>(response: &'a Response, key: &str) -> Option<&'a str> {
+    response
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, value)| value.as_str())
+}
+
+fn get_f64(response: &Response, key: &str) -> f64 {
+    get(response, key)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0.0)
+}
+
+impl Session {
+    async fn read_line(&mut self) -> Result<String, Error> {
+        match self.lines.next_line().await {
+            Ok(Some(line)) => Ok(line),
+            Ok(None) => Err(Error::NotRunning),
+            Err(e) => Err(Error::Protocol(e.to_string())),
+        }
+    }
+
+    async fn command(&mut self, command: &str) -> Result<Response, Error> {
+        self.writer
+            .write_all(format!("{}\n", command).as_bytes())
+            .await
+            .map_err(|_| Error::NotRunning)?;
+
+        let mut response = vec![];
+        loop {
+            let line = self.read_line().await?;
+            if line == "OK" {
+                return Ok(response);
+            }
+            // ACK [error@command_listNum] {current_command} message_text
+            if let Some(error) = line.strip_prefix("ACK ") {
+                tracing::debug!("MPD rejected {}: {}", command, error);
+                return Err(Error::BadArgument(error.to_string()));
+            }
+            match line.split_once(": ") {
+                Some((key, value)) => response.push((key.to_string(), value.to_string())),
+                None => return Err(Error::Protocol(format!("unexpected line: {}", line))),
+            }
+        }
+    }
+}
+
+impl Mpd {
+    pub fn new(settings: MpdSettings) -> Self {
+        Mpd { settings }
+    }
+
+    async fn connect(&self) -> Result<Session, Error> {
+        let address = (self.settings.host.as_str(), self.settings.port);
+        let stream = match tokio::time::timeout(TIMEOUT, TcpStream::connect(address)).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(_)) => return Err(Error::NotRunning),
+            Err(_) => return Err(Error::Timeout),
+        };
+
+        let (reader, writer) = stream.into_split();
+        let mut session = Session {
+            lines: BufReader::new(reader).lines(),
+            writer,
+        };
+
+        let greeting = session.read_line().await?;
+        if !greeting.starts_with("OK MPD ") {
+            return Err(Error::Protocol(format!(
+                "unexpected greeting: {}",
+                greeting
+            )));
+        }
+
+        if let Some(password) = &self.settings.password {
+            session
+                .command(&format!("password \"{}\"", escape(password)))
+                .await?;
+        }
+
+        Ok(session)
+    }
+
+    /// Runs a single command on a fresh connection.
+    async fn command(&self, command: &str) -> Result<Response, Error> {
+        let run = async {
+            let mut session = self.connect().await?;
+            session.command(command).await
+        };
+
+        tokio::time::timeout(TIMEOUT, run)
+            .await
+            .map_err(|_| Error::Timeout)?
+    }
+
+    async fn volume(&self) -> Result<i64, Error> {
+        let status = self.command("status").await?;
+        match get(&status, "volume").and_then(|volume| volume.parse().ok()) {
+            Some(volume) if volume >= 0 => Ok(volume),
+            // MPD reports -1 when the output has no mixer.
+            _ => Err(Error::PropertyUnavailable("volume".to_string())),
+        }
+    }
+
+    async fn set_volume_to(&self, volume: i64) -> Result<(), Error> {
+        self.command(&format!("setvol {}", volume.clamp(0, 100)))
+            .await?;
+        Ok(())
+    }
+}
+
+fn escape(argument: &str) -> String {
+    argument.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn title(song: &Response) -> Option<String> {
+    get(song, "Title")
+        .or_else(|| get(song, "Name"))
+        .map(str::to_string)
+}
+
+#[async_trait]
+impl MediaPlayer for Mpd {
+    async fn toggle_pause(&self) -> Result<(), Error> {
+        let status = self.command("status").await?;
+        let command = match get(&status, "state") {
+            Some("play") => "pause 1",
+            Some("pause") => "pause 0",
+            // Stopped, start from the current song.
+            _ => "play",
+        };
+        self.command(command).await?;
+        Ok(())
+    }
+
+    async fn seek(&self, offset: f64) -> Result<(), Error> {
+        self.command(&format!("seekcur {:+}", offset)).await?;
+        Ok(())
+    }
+
+    async fn seek_to(&self, position: f64) -> Result<(), Error> {
+        self.command(&format!("seekcur {}", position.max(0.0)))
+            .await?;
+        Ok(())
+    }
+
+    async fn set_volume(&self, volume: i16) -> Result<(), Error> {
+        self.set_volume_to(i64::from(volume)).await
+    }
+
+    async fn volume_up(&self) -> Result<(), Error> {
+        let volume = self.volume().await?;
+        self.set_volume_to(volume + VOLUME_STEP).await
+    }
+
+    async fn volume_down(&self) -> Result<(), Error> {
+        let volume = self.volume().await?;
+        self.set_volume_to(volume - VOLUME_STEP).await
+    }
+
+    async fn set_subtitle(&self, _id: i64) -> Result<(), Error> {
+        Err(Error::BadArgument("MPD has no subtitles".to_string()))
+    }
+
+    async fn status(&self) -> Result<Status, Error> {
+        let (status, song) = tokio::time::timeout(TIMEOUT, async {
+            let mut session = self.connect().await?;
+            let status = session.command("status").await?;
+            let song = session.command("currentsong").await?;
+            Ok::<_, Error>((status, song))
+        })
+        .await
+        .map_err(|_| Error::Timeout)??;
+
+        let file = get(&song, "file");
+        let title = title(&song).or_else(|| file.map(str::to_string));
+
+        Ok(Status {
+            paused: get(&status, "state") != Some("play"),
+            volume: get_f64(&status, "volume").max(0.0),
+            position: get_f64(&status, "elapsed"),
+            length: get_f64(&status, "duration"),
+            title: Value::from(title).to_string(),
+            file: Value::from(file).to_string(),
+            subtitle: None,
+            subtitles: vec![],
+            ..Default::default()
+        })
+    }
+
+    async fn playlist(&self) -> Result<Vec<PlaylistEntry>, Error> {
+        let (status, songs) = tokio::time::timeout(TIMEOUT, async {
+            let mut session = self.connect().await?;
+            let status = session.command("status").await?;
+            let songs = session.command("playlistinfo").await?;
+            Ok::<_, Error>((status, songs))
+        })
+        .await
+        .map_err(|_| Error::Timeout)??;
+
+        let current = get(&status, "song");
+        let playing = get(&status, "state") == Some("play");
+
+        // Every song starts with its `file` line.
+        let mut entries: Vec<PlaylistEntry> = vec![];
+        let mut song: Response = vec![];
+        for (key, value) in songs
+            .into_iter()
+            .chain([("file".to_string(), String::new())])
+        {
+            if key == "file" && !song.is_empty() {
+                let is_current = get(&song, "Pos").is_some() && get(&song, "Pos") == current;
+                entries.push(PlaylistEntry {
+                    filename: get(&song, "file").unwrap_or_default().to_string(),
+                    title: title(&song),
+                    current: is_current,
+                    playing: is_current && playing,
+                });
+                song.clear();
+            }
+            song.push((key, value));
+        }
+
+        Ok(entries)
+    }
+
+    async fn playlist_next(&self) -> Result<(), Error> {
+        self.command("next").await?;
+        Ok(())
+    }
+
+    async fn playlist_previous(&self) -> Result<(), Error> {
+        self.command("previous").await?;
+        Ok(())
+    }
+
+    async fn wait_for_change(&self) -> Result<Vec<String>, Error> {
+        // No timeout here, `idle` blocks until something changes.
+        let mut session = self.connect().await?;
+        let changed = session.command("idle").await?;
+        Ok(changed
+            .into_iter()
+            .filter(|(key, _)| key == "changed")
+            .map(|(_, subsystem)| subsystem)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Fake MPD answering every command from `reply`, recording what it was sent.
+    async fn serve(reply: fn(&str) -> &'static str) -> (Mpd, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let commands = Arc::new(Mutex::new(vec![]));
+
+        let received = commands.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let (reader, mut writer) = stream.into_split();
+                let received = received.clone();
+                tokio::spawn(async move {
+                    writer.write_all(b"OK MPD 0.23.5\n").await.unwrap();
+                    let mut lines = BufReader::new(reader).lines();
+                    while let Ok(Some(command)) = lines.next_line().await {
+                        let response = reply(&command);
+                        received.lock().push(command);
+                        writer.write_all(response.as_bytes()).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        let mpd = Mpd::new(MpdSettings {
+            host: "127.0.0.1".to_string(),
+            port,
+            password: None,
+        });
+        (mpd, commands)
+    }
+
+    fn library(command: &str) -> &'static str {
+        match command {
+            "status" => "volume: 40\nstate: play\nsong: 1\nelapsed: 12.5\nduration: 200.0\nOK\n",
+            "currentsong" => "file: music/two.flac\nTitle: Two\nPos: 1\nOK\n",
+            "playlistinfo" => concat!(
+                "file: music/one.flac\nTitle: One\nPos: 0\n",
+                "file: music/two.flac\nPos: 1\n",
+                "file: stream.m3u\nName: Radio\nPos: 2\n",
+                "OK\n",
+            ),
+            "seekcur +5" | "seekcur -2.5" | "seekcur 30" => "OK\n",
+            _ => "ACK [5@0] {} unknown command\n",
+        }
+    }
+
+    #[tokio::test]
+    async fn parses_status() {
+        let (mpd, _) = serve(library).await;
+
+        let status = mpd.status().await.unwrap();
+        assert!(!status.paused);
+        assert_eq!(status.volume, 40.0);
+        assert_eq!(status.position, 12.5);
+        assert_eq!(status.length, 200.0);
+        assert_eq!(status.title, "\"Two\"");
+        assert_eq!(status.file, "\"music/two.flac\"");
+    }
+
+    #[tokio::test]
+    async fn parses_playlist() {
+        let (mpd, _) = serve(library).await;
+
+        let entries = mpd.playlist().await.unwrap();
+        let entries: Vec<(&str, Option<&str>, bool, bool)> = entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.filename.as_str(),
+                    entry.title.as_deref(),
+                    entry.current,
+                    entry.playing,
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("music/one.flac", Some("One"), false, false),
+                ("music/two.flac", None, true, true),
+                ("stream.m3u", Some("Radio"), false, false),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn seeks_with_explicit_sign() {
+        let (mpd, commands) = serve(library).await;
+
+        mpd.seek(5.0).await.unwrap();
+        mpd.seek(-2.5).await.unwrap();
+        mpd.seek_to(30.0).await.unwrap();
+
+        // Without the `+` MPD would seek to the absolute position.
+        assert_eq!(
+            *commands.lock(),
+            ["seekcur +5", "seekcur -2.5", "seekcur 30"]
+        );
+    }
+
+    #[tokio::test]
+    async fn ack_is_bad_argument() {
+        let (mpd, _) = serve(library).await;
+
+        let result = mpd.playlist_next().await;
+        assert!(matches!(result, Err(Error::BadArgument(_))));
+    }
+}
@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::sync::OnceCell;
+use zbus::fdo::DBusProxy;
+use zbus::zvariant::{ObjectPath, OwnedValue};
+use zbus::Connection;
+
+use crate::clients::MediaPlayer;
+use crate::clients::{Error, PlaylistEntry, Status};
+use crate::settings::MprisSettings;
+
+const PREFIX: &str = "org.mpris.MediaPlayer2.";
+const VOLUME_STEP: f64 = 0.02;
+/// MPRIS volumes are fractions, 1.0 being the player's normal volume.
+const MAX_VOLUME: f64 = 1.0;
+
+static SESSION: OnceCell<Connection> = OnceCell::const_new();
+
+#[zbus::proxy(
+    interface = "org.mpris.MediaPlayer2.Player",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+trait Player {
+    fn play_pause(&self) -> zbus::Result<()>;
+    fn next(&self) -> zbus::Result<()>;
+    fn previous(&self) -> zbus::Result<()>;
+    fn seek(&self, offset: i64) -> zbus::Result<()>;
+    fn set_position(&self, track_id: &ObjectPath<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> zbus::Result<String>;
+    #[zbus(property)]
+    fn volume(&self) -> zbus::Result<f64>;
+    #[zbus(property)]
+    fn set_volume(&self, value: f64) -> zbus::Result<()>;
+    #[zbus(property)]
+    fn position(&self) -> zbus::Result<i64>;
+    #[zbus(property)]
+    fn metadata(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+}
+
+async fn session() -> Result<&'static Connection, Error> {
+    SESSION
+        .get_or_try_init(Connection::session)
+        .await
+        .map_err(map_error)
+}
+
+fn map_error(e: zbus::Error) -> Error {
+    match e {
+        zbus::Error::InputOutput(_) => Error::NotRunning,
+        zbus::Error::MethodError(name, message, _)
+            if name.as_str() == "org.freedesktop.DBus.Error.ServiceUnknown" =>
+        {
+            tracing::debug!("MPRIS player went away: {:?}", message);
+            Error::NotRunning
+        }
+        zbus::Error::FDO(e) => match *e {
+            zbus::fdo::Error::ServiceUnknown(_) | zbus::fdo::Error::NameHasNoOwner(_) => {
+                Error::NotRunning
+            }
+            zbus::fdo::Error::NoReply(_) | zbus::fdo::Error::Timeout(_) => Error::Timeout,
+            zbus::fdo::Error::NotSupported(message) => Error::BadArgument(message),
+            e => Error::Protocol(e.to_string()),
+        },
+        e => Error::Protocol(e.to_string()),
+    }
+}
+
+/// Every `org.mpris.MediaPlayer2.*` name currently on the session bus, without the prefix.
+pub async fn list_players() -> Result<Vec<String>, Error> {
+    let dbus = DBusProxy::new(session().await?).await.map_err(map_error)?;
+    let names = dbus.list_names().await.map_err(|e| map_error(e.into()))?;
+
+    let mut players: Vec<String> = names
+        .iter()
+        .filter_map(|name| name.as_str().strip_prefix(PREFIX))
+        .map(str::to_string)
+        .collect();
+    players.sort();
+    Ok(players)
+}
+
+fn micros(seconds: f64) -> i64 {
+    (seconds * 1_000_000.0).round() as i64
+}
+
+fn seconds(micros: i64) -> f64 {
+    micros as f64 / 1_000_000.0
+}
+
+/// Any desktop player exposing MPRIS on the session bus.
+pub struct Mpris {
+    settings: MprisSettings,
+}
+
+impl Mpris {
+    pub fn new(settings: MprisSettings) -> Self {
+        Mpris { settings }
+    }
+
+    /// Proxy for the configured player, or the first one found if none is configured.
+    async fn proxy(&self) -> Result<PlayerProxy<'static>, Error> {
+        let player = match &self.settings.player {
+            Some(player) => player.clone(),
+            None => list_players()
+                .await?
+                .into_iter()
+                .next()
+                .ok_or(Error::NotRunning)?,
+        };
+
+        PlayerProxy::builder(session().await?)
+            .destination(format!("{}{}", PREFIX, player))
+            .map_err(map_error)?
+            .build()
+            .await
+            .map_err(map_error)
+    }
+
+    async fn metadata(proxy: &PlayerProxy<'_>) -> Result<HashMap<String, Value>, Error> {
+        let metadata = proxy.metadata().await.map_err(map_error)?;
+        Ok(metadata
+            .into_iter()
+            .map(|(key, value)| (key, to_json(&value)))
+            .collect())
+    }
+}
+
+/// Status of a player from its metadata and properties, `position` in microseconds.
+fn to_status(
+    metadata: &HashMap<String, Value>,
+    playback: &str,
+    volume: f64,
+    position: i64,
+) -> Status {
+    let field = |key: &str| metadata.get(key).cloned().unwrap_or_default();
+
+    Status {
+        paused: playback != "Playing",
+        volume: volume * 100.0,
+        position: seconds(position),
+        length: seconds(field("mpris:length").as_i64().unwrap_or(0)),
+        title: field("xesam:title").to_string(),
+        file: field("xesam:url").to_string(),
+        subtitle: None,
+        subtitles: vec![],
+        ..Default::default()
+    }
+}
+
+/// The track a player has loaded, if any.
+fn current_entry(metadata: &HashMap<String, Value>, playback: &str) -> Option<PlaylistEntry> {
+    let url = metadata.get("xesam:url").and_then(Value::as_str)?;
+
+    Some(PlaylistEntry {
+        filename: url.to_string(),
+        title: metadata
+            .get("xesam:title")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        current: true,
+        playing: playback == "Playing",
+    })
+}
+
+fn to_json(value: &OwnedValue) -> Value {
+    use zbus::zvariant::Value as Variant;
+
+    match &**value {
+        Variant::Str(s) => Value::from(s.as_str()),
+        Variant::ObjectPath(path) => Value::from(path.as_str()),
+        Variant::I64(n) => Value::from(*n),
+        Variant::U64(n) => Value::from(*n),
+        Variant::I32(n) => Value::from(*n),
+        Variant::U32(n) => Value::from(*n),
+        Variant::F64(n) => Value::from(*n),
+        Variant::Bool(b) => Value::from(*b),
+        Variant::Array(array) => Value::Array(
+            array
+                .iter()
+                .filter_map(|item| OwnedValue::try_from(item).ok())
+                .map(|item| to_json(&item))
+                .collect(),
+        ),
+        _ => Value::Null,
+    }
+}
+
+#[async_trait]
+impl MediaPlayer for Mpris {
+    async fn toggle_pause(&self) -> Result<(), Error> {
+        self.proxy().await?.play_pause().await.map_err(map_error)
+    }
+
+    async fn seek(&self, offset: f64) -> Result<(), Error> {
+        self.proxy()
+            .await?
+            .seek(micros(offset))
+            .await
+            .map_err(map_error)
+    }
+
+    async fn seek_to(&self, position: f64) -> Result<(), Error> {
+        let proxy = self.proxy().await?;
+        let metadata = Self::metadata(&proxy).await?;
+        let track = metadata
+            .get("mpris:trackid")
+            .and_then(Value::as_str)
+            .ok_or_else(|| Error::PropertyUnavailable("mpris:trackid".to_string()))?;
+        let track = ObjectPath::try_from(track).map_err(|e| Error::Protocol(e.to_string()))?;
+
+        proxy
+            .set_position(&track, micros(position))
+            .await
+            .map_err(map_error)
+    }
+
+    async fn set_volume(&self, volume: i16) -> Result<(), Error> {
+        if volume < 0 {
+            return Err(Error::BadArgument(format!(
+                "Volume must not be negative, got {}",
+                volume
+            )));
+        }
+        self.proxy()
+            .await?
+            .set_volume((f64::from(volume) / 100.0).min(MAX_VOLUME))
+            .await
+            .map_err(map_error)
+    }
+
+    async fn volume_up(&self) -> Result<(), Error> {
+        let proxy = self.proxy().await?;
+        let volume = proxy.volume().await.map_err(map_error)?;
+        proxy
+            .set_volume((volume + VOLUME_STEP).min(MAX_VOLUME))
+            .await
+            .map_err(map_error)
+    }
+
+    async fn volume_down(&self) -> Result<(), Error> {
+        let proxy = self.proxy().await?;
+        let volume = proxy.volume().await.map_err(map_error)?;
+        proxy
+            .set_volume((volume - VOLUME_STEP).max(0.0))
+            .await
+            .map_err(map_error)
+    }
+
+    async fn set_subtitle(&self, _id: i64) -> Result<(), Error> {
+        Err(Error::BadArgument(
+            "MPRIS players don't expose subtitles".to_string(),
+        ))
+    }
+
+    async fn status(&self) -> Result<Status, Error> {
+        let proxy = self.proxy().await?;
+        let metadata = Self::metadata(&proxy).await?;
+        let playback = proxy.playback_status().await.map_err(map_error)?;
+        // Not every player implements these, report them as zero like mpv does with nothing loaded.
+        let volume = proxy.volume().await.unwrap_or(0.0);
+        let position = proxy.position().await.unwrap_or(0);
+
+        Ok(to_status(&metadata, &playback, volume, position))
+    }
+
+    async fn playlist(&self) -> Result<Vec<PlaylistEntry>, Error> {
+        // The optional TrackList interface is rarely implemented, only report what's playing.
+        let proxy = self.proxy().await?;
+        let metadata = Self::metadata(&proxy).await?;
+        let playback = proxy.playback_status().await.map_err(map_error)?;
+
+        Ok(current_entry(&metadata, &playback).into_iter().collect())
+    }
+
+    async fn playlist_next(&self) -> Result<(), Error> {
+        self.proxy().await?.next().await.map_err(map_error)
+    }
+
+    async fn playlist_previous(&self) -> Result<(), Error> {
+        self.proxy().await?.previous().await.map_err(map_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn metadata(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn converts_microseconds() {
+        assert_eq!(micros(1.5), 1_500_000);
+        assert_eq!(micros(-10.0), -10_000_000);
+        assert_eq!(micros(0.0000004), 0);
+        assert_eq!(seconds(2_500_000), 2.5);
+        assert_eq!(seconds(micros(123.456)), 123.456);
+    }
+
+    #[test]
+    fn maps_metadata_to_status() {
+        let metadata = metadata(json!({
+            "mpris:trackid": "/org/mpris/MediaPlayer2/Track/1",
+            "mpris:length": 90_000_000,
+            "xesam:title": "Episode 1",
+            "xesam:url": "file:///media/episode1.mkv",
+        }));
+
+        let status = to_status(&metadata, "Playing", 0.5, 30_000_000);
+        assert!(!status.paused);
+        assert_eq!(status.volume, 50.0);
+        assert_eq!(status.position, 30.0);
+        assert_eq!(status.length, 90.0);
+        assert_eq!(status.title, "\"Episode 1\"");
+        assert_eq!(status.file, "\"file:///media/episode1.mkv\"");
+
+        assert!(to_status(&metadata, "Paused", 0.5, 0).paused);
+        assert!(to_status(&metadata, "Stopped", 0.5, 0).paused);
+    }
+
+    #[test]
+    fn missing_metadata_is_empty() {
+        let status = to_status(&HashMap::new(), "Stopped", 0.0, 0);
+        assert_eq!(status.length, 0.0);
+        assert_eq!(status.title, "null");
+        assert_eq!(status.file, "null");
+        assert!(current_entry(&HashMap::new(), "Stopped").is_none());
+    }
+
+    #[test]
+    fn current_entry_follows_playback() {
+        let metadata = metadata(json!({
+            "xesam:title": "Episode 1",
+            "xesam:url": "file:///media/episode1.mkv",
+        }));
+
+        let entry = current_entry(&metadata, "Playing").unwrap();
+        assert_eq!(entry.filename, "file:///media/episode1.mkv");
+        assert_eq!(entry.title.as_deref(), Some("Episode 1"));
+        assert!(entry.current);
+        assert!(entry.playing);
+
+        for playback in ["Paused", "Stopped"] {
+            let entry = current_entry(&metadata, playback).unwrap();
+            assert!(entry.current);
+            assert!(!entry.playing);
+        }
+    }
+
+    #[test]
+    fn maps_errors() {
+        let fdo = |e: zbus::fdo::Error| map_error(zbus::Error::FDO(Box::new(e)));
+
+        assert!(matches!(
+            map_error(zbus::Error::InputOutput(
+                std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into()
+            )),
+            Error::NotRunning
+        ));
+        assert!(matches!(
+            fdo(zbus::fdo::Error::ServiceUnknown("gone".to_string())),
+            Error::NotRunning
+        ));
+        assert!(matches!(
+            fdo(zbus::fdo::Error::NameHasNoOwner("gone".to_string())),
+            Error::NotRunning
+        ));
+        assert!(matches!(
+            fdo(zbus::fdo::Error::NoReply("slow".to_string())),
+            Error::Timeout
+        ));
+        assert!(matches!(
+            fdo(zbus::fdo::Error::Timeout("slow".to_string())),
+            Error::Timeout
+        ));
+        assert!(matches!(
+            fdo(zbus::fdo::Error::NotSupported("no seeking".to_string())),
+            Error::BadArgument(message) if message == "no seeking"
+        ));
+        assert!(matches!(
+            fdo(zbus::fdo::Error::Failed("broken".to_string())),
+            Error::Protocol(_)
+        ));
+        assert!(matches!(
+            map_error(zbus::Error::Failure("broken".to_string())),
+            Error::Protocol(_)
+        ));
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use serde_json::{json, Value};
+
+use super::{instances, pipe, subtitles, Error, Subtitle};
+use crate::library::{self, Kind};
+use crate::settings;
+
+/// A subtitle file found next to the playing file.
+struct Available {
+    path: PathBuf,
+    // Taken from a suffix like `movie.en.srt`.
+    lang: Option<String>,
+}
+
+lazy_static! {
+    // Per instance, `-(index + 1)` is the id a client uses to load one of these.
+    static ref AVAILABLE: RwLock<HashMap<String, Vec<Available>>> = RwLock::new(HashMap::new());
+}
+
+// Anything bigger is not a subtitle file.
+pub const MAX_SUBTITLE_SIZE: u64 = 10 * 1024 * 1024;
+
+fn is_subtitle(path: &Path) -> bool {
+    Kind::of(path) == Kind::Subtitle
+}
+
+/// Copies an uploaded subtitle into the cache so mpv can read it after the
+/// upload's temporary file is gone. Returns where it ended up.
+pub fn store_subtitle(name: &str, upload: &Path) -> Result<PathBuf, Error> {
+    // Only keep the file name, the client doesn't get to pick directories.
+    let name = Path::new(name)
+        .file_name()
+        .ok_or_else(|| Error::BadArgument(format!("Invalid file name: {}", name)))?;
+    if !is_subtitle(Path::new(name)) {
+        return Err(Error::BadArgument(format!(
+            "Not a subtitle file: {}",
+            name.to_string_lossy()
+        )));
+    }
+
+    let dir = settings::get_cache_dir("subtitles");
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| Error::BadArgument(format!("Can't create {}: {}", dir.display(), e)))?;
+
+    let path = dir.join(name);
+    std::fs::copy(upload, &path)
+        .map_err(|e| Error::BadArgument(format!("Can't store {}: {}", path.display(), e)))?;
+    Ok(path)
+}
+
+/// Loads an external subtitle file and returns the subtitle list including it.
+/// Only uploads stored by `store_subtitle` and files in the media roots are
+/// allowed.
+pub async fn add_subtitle(path: &Path, select: bool) -> Result<Vec<Subtitle>, Error> {
+    let path = allowed_path(path)?;
+    load_subtitle(&path, select).await
+}
+
+fn allowed_path(path: &Path) -> Result<PathBuf, Error> {
+    let uploads = std::fs::canonicalize(settings::get_cache_dir("subtitles"));
+    match std::fs::canonicalize(path) {
+        Ok(path) if uploads.is_ok_and(|uploads| path.starts_with(uploads)) => Ok(path),
+        _ => library::resolve(&path.to_string_lossy()),
+    }
+}
+
+async fn load_subtitle(path: &Path, select: bool) -> Result<Vec<Subtitle>, Error> {
+    if !is_subtitle(path) {
+        return Err(Error::BadArgument(format!(
+            "Not a subtitle file: {}",
+            path.display()
+        )));
+    }
+    if !path.is_file() {
+        return Err(Error::BadArgument(format!(
+            "No such file: {}",
+            path.display()
+        )));
+    }
+
+    let flag = if select { "select" } else { "auto" };
+    pipe::command(json!(["sub-add", path.to_string_lossy(), flag])).await?;
+
+    // The observer hears about the new track a moment later, ask directly
+    // so the caller sees it right away.
+    let tracks = pipe::get_property("track-list").await?;
+    Ok(subtitles(&tracks))
+}
+
+/// Looks for subtitles belonging to the file mpv is playing, either in its
+/// directory or one of the configured subtitle folders next to it. The
+/// directories are read on the blocking pool.
+pub async fn discover(path: Option<String>) {
+    let found = match path.clone() {
+        Some(path) => tokio::task::spawn_blocking(move || find_subtitles(Path::new(&path)))
+            .await
+            .unwrap_or_else(|e| {
+                tracing::debug!("Error looking for subtitles: {:?}", e);
+                vec![]
+            }),
+        None => vec![],
+    };
+    tracing::debug!("Found {} subtitle files next to {:?}", found.len(), path);
+    AVAILABLE.write().insert(instances::current(), found);
+}
+
+fn find_subtitles(path: &Path) -> Vec<Available> {
+    let (Some(dir), Some(stem)) = (path.parent(), path.file_stem()) else {
+        return vec![];
+    };
+    let stem = stem.to_string_lossy();
+
+    let folders = settings::load_settings()
+        .mpv
+        .and_then(|mpv| mpv.subtitle_folders)
+        .unwrap_or_else(|| vec!["Subs".to_string()]);
+
+    let mut found = vec![];
+    for dir in std::iter::once(dir.to_path_buf()).chain(folders.iter().map(|f| dir.join(f))) {
+        // Streams and missing folders simply have nothing to offer.
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut matches: Vec<Available> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file| is_subtitle(file))
+            .filter_map(|file| {
+                let lang = language(&file, &stem)?;
+                let lang = (!lang.is_empty()).then(|| lang.to_string());
+                Some(Available { path: file, lang })
+            })
+            .collect();
+        matches.sort_by(|a, b| a.path.cmp(&b.path));
+        found.extend(matches);
+    }
+
+    found
+}
+
+/// `Some("")` for `movie.srt`, `Some("en")` for `movie.en.srt`, `None` when
+/// the file belongs to something else.
+fn language<'a>(file: &'a Path, stem: &str) -> Option<&'a str> {
+    let name = file.file_stem()?.to_str()?;
+    match name.strip_prefix(stem)? {
+        "" => Some(""),
+        rest => rest.strip_prefix('.'),
+    }
+}
+
+/// Files found on disk that mpv hasn't loaded as a track yet.
+pub fn not_loaded(tracks: &Value) -> Vec<Subtitle> {
+    let loaded: Vec<&str> = tracks
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|track| track["external-filename"].as_str())
+        .collect();
+
+    let mut subtitles = vec![];
+    let found = AVAILABLE.read();
+    let found = found.get(&instances::current()).map(Vec::as_slice);
+    for (index, available) in found.unwrap_or_default().iter().enumerate() {
+        if loaded
+            .iter()
+            .any(|loaded| Path::new(loaded) == available.path)
+        {
+            continue;
+        }
+
+        let name = available
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let title = match &available.lang {
+            Some(lang) => format!("{} - {}", lang, name),
+            None => name,
+        };
+
+        subtitles.push(Subtitle {
+            id: -(index as i64 + 1),
+            title,
+            loaded: false,
+        });
+    }
+
+    subtitles
+}
+
+/// Loads and selects a file from `not_loaded` by its id.
+pub async fn load_available(id: i64) -> Result<(), Error> {
+    // Written as `-(id + 1)` so no id overflows on the way to an index.
+    let path = id
+        .checked_add(1)
+        .and_then(|id| usize::try_from(-id).ok())
+        .and_then(|index| {
+            AVAILABLE
+                .read()
+                .get(&instances::current())?
+                .get(index)
+                .map(|available| available.path.clone())
+        })
+        .ok_or_else(|| Error::BadArgument(format!("Unknown subtitle: {}", id)))?;
+
+    // Found next to the playing file, which needn't be in a media root.
+    load_subtitle(&path, true).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_subtitles_to_the_playing_file() {
+        let cases = [
+            ("movie.srt", Some("")),
+            ("movie.en.srt", Some("en")),
+            ("movie.pt-BR.ass", Some("pt-BR")),
+            ("movie.en.forced.srt", Some("en.forced")),
+            ("movie2.srt", None),
+            ("movies.en.srt", None),
+            ("other.en.srt", None),
+        ];
+        for (file, lang) in cases {
+            assert_eq!(language(Path::new(file), "movie"), lang, "{}", file);
+        }
+    }
+
+    #[tokio::test]
+    async fn numbers_files_not_loaded_yet() {
+        instances::scope("external-test".to_string(), async {
+            let available = [
+                "/media/movie.srt",
+                "/media/movie.en.srt",
+                "/media/Subs/movie.de.srt",
+            ]
+            .into_iter()
+            .zip([None, Some("en"), Some("de")])
+            .map(|(path, lang)| Available {
+                path: PathBuf::from(path),
+                lang: lang.map(str::to_string),
+            })
+            .collect();
+            AVAILABLE.write().insert(instances::current(), available);
+
+            let tracks = json!([
+                {"type": "sub", "external-filename": "/media/movie.en.srt"},
+                {"type": "audio"},
+            ]);
+            let subtitles: Vec<(i64, String, bool)> = not_loaded(&tracks)
+                .into_iter()
+                .map(|subtitle| (subtitle.id, subtitle.title, subtitle.loaded))
+                .collect();
+            assert_eq!(
+                subtitles,
+                [
+                    (-1, "movie.srt".to_string(), false),
+                    (-3, "de - movie.de.srt".to_string(), false),
+                ]
+            );
+
+            for id in [0, 5, i64::MAX, i64::MIN] {
+                assert!(matches!(
+                    load_available(id).await,
+                    Err(Error::BadArgument(_))
+                ));
+            }
+        })
+        .await;
+    }
+}
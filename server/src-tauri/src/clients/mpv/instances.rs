@@ -0,0 +1,174 @@
+use std::future::Future;
+
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use serde::Serialize;
+
+use super::process;
+use crate::settings::{load_settings, MpvSettings};
+
+/// Name of the instance listening on `MpvSettings::pipe`.
+pub const DEFAULT: &str = "default";
+
+tokio::task_local! {
+    // Instance the mpv functions talk to, set by `scope`.
+    static CURRENT: String;
+}
+
+lazy_static! {
+    // Kept fresh by `refresh` so lookups on every request and command don't
+    // read settings or scan for sockets.
+    static ref CACHE: RwLock<Option<Cache>> = RwLock::new(None);
+}
+
+struct Cache {
+    default_name: String,
+    instances: Vec<Instance>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Instance {
+    pub name: String,
+    pub pipe: String,
+    /// Found as an `mpvsocket*` socket rather than configured.
+    pub discovered: bool,
+}
+
+/// Runs `future` against the named instance instead of the default one.
+pub async fn scope<F: Future>(name: String, future: F) -> F::Output {
+    CURRENT.scope(name, future).await
+}
+
+/// The instance in scope, otherwise the configured default.
+pub fn current() -> String {
+    CURRENT
+        .try_with(|name| name.clone())
+        .unwrap_or_else(|_| default_name())
+}
+
+pub fn default_name() -> String {
+    cached(|cache| cache.default_name.clone())
+}
+
+/// Configured instances followed by discovered ones, configured names win.
+pub fn instances() -> Vec<Instance> {
+    let mut instances = cached(|cache| cache.instances.clone());
+    // An mpv the server launched itself takes precedence, even before the
+    // next refresh.
+    if let Some(ipc) = process::ipc_path() {
+        if let Some(default) = instances
+            .iter_mut()
+            .find(|instance| instance.name == DEFAULT)
+        {
+            default.pipe = ipc;
+        }
+    }
+    instances
+}
+
+/// Reloads the default name and the instances from settings and the
+/// discovery directory. Blocks on the disk.
+pub fn refresh() {
+    let mpv = load_settings().mpv;
+    let default_name = mpv
+        .as_ref()
+        .and_then(|mpv| mpv.default_instance.clone())
+        .unwrap_or_else(|| DEFAULT.to_string());
+
+    *CACHE.write() = Some(Cache {
+        default_name,
+        instances: mpv.map(load).unwrap_or_default(),
+    });
+}
+
+fn cached<T>(get: impl Fn(&Cache) -> T) -> T {
+    if let Some(cache) = CACHE.read().as_ref() {
+        return get(cache);
+    }
+    // Nothing observed yet.
+    refresh();
+    get(CACHE.read().as_ref().unwrap())
+}
+
+fn load(mpv: MpvSettings) -> Vec<Instance> {
+    let mut instances = vec![Instance {
+        name: DEFAULT.to_string(),
+        // Also keeps the launched mpv's socket from being discovered again.
+        pipe: process::ipc_path().unwrap_or(mpv.pipe),
+        discovered: false,
+    }];
+    for instance in mpv.instances.into_iter().flatten() {
+        instances.push(Instance {
+            name: instance.name,
+            pipe: instance.pipe,
+            discovered: false,
+        });
+    }
+
+    for (name, pipe) in mpv.discover.as_deref().map(discover).unwrap_or_default() {
+        let known = instances
+            .iter()
+            .any(|instance| instance.name == name || same_pipe(&instance.pipe, &pipe));
+        if !known {
+            instances.push(Instance {
+                name,
+                pipe,
+                discovered: true,
+            });
+        }
+    }
+
+    instances
+}
+
+pub fn exists(name: &str) -> bool {
+    pipe(name).is_some()
+}
+
+pub fn pipe(name: &str) -> Option<String> {
+    instances()
+        .into_iter()
+        .find(|instance| instance.name == name)
+        .map(|instance| instance.pipe)
+}
+
+/// `mpvsocket*` entries in `dir`, named after the file.
+fn discover(dir: &str) -> Vec<(String, String)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut found: Vec<(String, String)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(is_socket)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            name.starts_with("mpvsocket")
+                .then(|| (name, entry.path().to_string_lossy().to_string()))
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+#[cfg(unix)]
+fn is_socket(entry: &std::fs::DirEntry) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    entry
+        .file_type()
+        .map(|file_type| file_type.is_socket())
+        .unwrap_or(false)
+}
+
+// Everything listed under `\\.\pipe\` is a pipe.
+#[cfg(windows)]
+fn is_socket(_entry: &std::fs::DirEntry) -> bool {
+    true
+}
+
+fn same_pipe(a: &str, b: &str) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
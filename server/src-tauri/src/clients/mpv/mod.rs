@@ -1,259 +1,640 @@
 use std::ops::{Add, Sub};
 
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
-mod pipe;
+use crate::library;
+use crate::settings::{load_settings, try_write_settings, SkipSettings};
 
-pub async fn toggle_pause() {
+mod external;
+mod instances;
+mod observer;
+mod pipe;
+mod player;
+mod process;
+mod screenshot;
+
+pub use super::{Chapter, Error, PlaylistEntry, Status, Subtitle, Track};
+pub use external::{add_subtitle, store_subtitle, MAX_SUBTITLE_SIZE};
+pub use instances::{current as current_instance, exists as instance_exists, scope, Instance};
+pub use observer::{observe, subscribe_events, watch_status};
+pub use player::Mpv;
+pub use process::{quit, start};
+pub use screenshot::{
+    delete_screenshot, list_screenshots, read_screenshot, screenshot_path, take_screenshot,
+    ImageFormat,
+};
+
+pub async fn toggle_pause() -> Result<(), Error> {
     tracing::debug!("toggling pause");
-    #[cfg(windows)]
-    let client = pipe::get_client();
-
-    #[cfg(unix)]
-    let client = pipe::get_client().await;
-
-    if client.is_err() {
-        tracing::debug!("Error getting client: {:?}", client.err().unwrap());
-        return;
-    }
 
-    let mut client = client.unwrap();
-
-    let is_paused = pipe::get_property(&mut client, "pause")
-        .await
-        .as_bool()
-        .unwrap();
+    let is_paused = get_bool("pause").await?;
 
     tracing::debug!("is_paused: {}", !is_paused);
 
-    pipe::set_bool_property(&mut client, "pause", !is_paused).await;
+    pipe::set_property("pause", json!(!is_paused)).await
 }
 
-pub async fn set_subtitle(id: i64) {
-    #[cfg(windows)]
-    let client = pipe::get_client();
+pub async fn set_subtitle(id: i64) -> Result<(), Error> {
+    if id < 0 {
+        return external::load_available(id).await;
+    }
+    pipe::set_property("sid", json!(id)).await
+}
 
-    #[cfg(unix)]
-    let client = pipe::get_client().await;
+pub async fn disable_subtitles() -> Result<(), Error> {
+    pipe::set_property("sid", json!("no")).await
+}
 
-    if client.is_err() {
-        tracing::debug!("Error getting client: {:?}", client.err().unwrap());
-        return;
+/// Dual-language subtitles, `None` turns the secondary track off.
+pub async fn set_secondary_subtitle(id: Option<i64>) -> Result<(), Error> {
+    match id {
+        Some(id) => pipe::set_property("secondary-sid", json!(id)).await,
+        None => pipe::set_property("secondary-sid", json!("no")).await,
     }
+}
 
-    let mut client = client.unwrap();
+pub async fn set_subtitle_delay(seconds: f64) -> Result<(), Error> {
+    pipe::set_property("sub-delay", json!(seconds)).await
+}
 
-    pipe::set_property(&mut client, "sid", id.to_string().as_str()).await;
+pub async fn adjust_subtitle_delay(seconds: f64) -> Result<(), Error> {
+    pipe::command(json!(["add", "sub-delay", seconds])).await?;
+    Ok(())
 }
 
-pub async fn set_volume(volume: i16) {
-    #[cfg(windows)]
-    let client = pipe::get_client();
-    #[cfg(unix)]
-    let client = pipe::get_client().await;
+pub async fn set_subtitle_scale(scale: f64) -> Result<(), Error> {
+    if !(scale > 0.0 && scale <= 100.0) {
+        return Err(Error::BadArgument(format!(
+            "Subtitle scale must be between 0 and 100, got {}",
+            scale
+        )));
+    }
+    pipe::set_property("sub-scale", json!(scale)).await
+}
 
-    if client.is_err() {
-        tracing::debug!("Error getting client: {:?}", client.err().unwrap());
-        return;
+/// Vertical position in percent of the screen height, 100 is the bottom.
+pub async fn set_subtitle_position(position: f64) -> Result<(), Error> {
+    if !(0.0..=150.0).contains(&position) {
+        return Err(Error::BadArgument(format!(
+            "Subtitle position must be between 0 and 150, got {}",
+            position
+        )));
     }
+    pipe::set_property("sub-pos", json!(position)).await
+}
 
-    let mut client = client.unwrap();
+pub async fn toggle_subtitle_visibility() -> Result<(), Error> {
+    pipe::command(json!(["cycle", "sub-visibility"])).await?;
+    Ok(())
+}
 
-    pipe::set_property(&mut client, "volume", volume.to_string().as_str()).await;
+pub async fn set_audio_track(id: i64) -> Result<(), Error> {
+    pipe::set_property("aid", json!(id)).await
 }
 
-pub async fn skip_backward() {
-    #[cfg(windows)]
-    let client = pipe::get_client();
-    #[cfg(unix)]
-    let client = pipe::get_client().await;
+pub async fn set_video_track(id: i64) -> Result<(), Error> {
+    pipe::set_property("vid", json!(id)).await
+}
 
-    if client.is_err() {
-        tracing::debug!("Error getting client: {:?}", client.err().unwrap());
-        return;
+/// Sets the volume, held within the configured limits.
+pub async fn set_volume(volume: i16) -> Result<(), Error> {
+    if volume < 0 {
+        return Err(Error::BadArgument(format!(
+            "Volume must not be negative, got {}",
+            volume
+        )));
     }
+    let volume = volume_limits().clamp(f64::from(volume));
+    pipe::set_property("volume", json!(volume)).await
+}
 
-    let mut client = client.unwrap();
+pub async fn toggle_mute() -> Result<(), Error> {
+    pipe::command(json!(["cycle", "mute"])).await?;
+    Ok(())
+}
 
-    let position = pipe::get_property(&mut client, "time-pos").await.as_f64();
-    if position.is_none() {
-        tracing::debug!("Error getting position");
-        return;
+/// Night mode caps the volume at `night_max` until it's turned off again.
+/// It's stored in settings so it survives restarts.
+pub async fn set_night_mode(enabled: bool) -> Result<(), Error> {
+    let write = tokio::task::spawn_blocking(move || {
+        let mut settings = load_settings();
+        let mut volume = settings.volume.unwrap_or_default();
+        volume.night_mode = enabled;
+        settings.volume = Some(volume);
+        try_write_settings(&settings)
+    });
+    write
+        .await
+        .map_err(|e| Error::Protocol(e.to_string()))?
+        .map_err(|e| Error::BadArgument(format!("Can't write settings: {}", e)))?;
+
+    // Bring a volume that is already too loud down right away.
+    let current = get_f64("volume").await?;
+    let limited = volume_limits().clamp(current);
+    if limited != current {
+        pipe::set_property("volume", json!(limited)).await?;
     }
-    let position = position.unwrap().sub(10.0f64);
+    Ok(())
+}
 
-    pipe::set_property(&mut client, "time-pos", position.to_string().as_str()).await;
+/// Toggles the normalization filter from settings, `dynaudnorm` by default.
+pub async fn toggle_normalization() -> Result<(), Error> {
+    let filter = load_settings()
+        .volume
+        .unwrap_or_default()
+        .normalize
+        .unwrap_or_else(|| "dynaudnorm".to_string());
+    let filter = format!("@{}:lavfi=[{}]", NORMALIZE_LABEL, filter);
+    pipe::command(json!(["af", "toggle", filter])).await?;
+    Ok(())
 }
 
-pub async fn skip_forward() {
-    #[cfg(windows)]
-    let client = pipe::get_client();
-    #[cfg(unix)]
-    let client = pipe::get_client().await;
+/// Skips by the step from the caller's settings, so skipping doesn't read them again.
+pub async fn skip_backward(skip: &SkipSettings, long: bool) -> Result<(), Error> {
+    seek(-skip.step(long)).await
+}
 
-    if client.is_err() {
-        tracing::debug!("Error getting client: {:?}", client.err().unwrap());
-        return;
-    }
+pub async fn skip_forward(skip: &SkipSettings, long: bool) -> Result<(), Error> {
+    seek(skip.step(long)).await
+}
 
-    let mut client = client.unwrap();
+pub async fn volume_up() -> Result<(), Error> {
+    let limits = volume_limits();
+    let volume = get_f64("volume").await?.add(limits.step);
 
-    let position = pipe::get_property(&mut client, "time-pos").await.as_f64();
-    if position.is_none() {
-        tracing::debug!("Error getting position");
-        return;
+    pipe::set_property("volume", json!(limits.clamp(volume))).await
+}
+
+pub async fn volume_down() -> Result<(), Error> {
+    let limits = volume_limits();
+    let volume = get_f64("volume").await?.sub(limits.step);
+
+    pipe::set_property("volume", json!(limits.clamp(volume))).await
+}
+
+/// Volume range and step in effect right now, night mode included.
+pub fn volume_limits() -> VolumeLimits {
+    let volume = load_settings().volume.unwrap_or_default();
+    let max = if volume.night_mode {
+        volume.max.min(volume.night_max)
+    } else {
+        volume.max
+    };
+
+    VolumeLimits {
+        step: volume.step,
+        min: volume.min.max(0.0),
+        max,
+        night_mode: volume.night_mode,
     }
-    let position = position.unwrap().add(10.0f64);
+}
 
-    pipe::set_property(&mut client, "time-pos", position.to_string().as_str()).await;
+pub async fn seek(offset: f64) -> Result<(), Error> {
+    seek_with(offset, SeekMode::Relative, None).await
 }
 
-pub async fn volume_up() {
-    #[cfg(windows)]
-    let client = pipe::get_client();
-    #[cfg(unix)]
-    let client = pipe::get_client().await;
+pub async fn seek_to(position: f64) -> Result<(), Error> {
+    seek_with(position, SeekMode::Absolute, None).await
+}
 
-    if client.is_err() {
-        tracing::debug!("Error getting client: {:?}", client.err().unwrap());
-        return;
+/// Seeks with mpv's `seek` command. `exact` picks between a precise seek and
+/// jumping to the nearest keyframe, `None` leaves it to mpv's `hr-seek` option.
+pub async fn seek_with(target: f64, mode: SeekMode, exact: Option<bool>) -> Result<(), Error> {
+    if matches!(mode, SeekMode::AbsolutePercent) && !(0.0..=100.0).contains(&target) {
+        return Err(Error::BadArgument(format!(
+            "Percent must be between 0 and 100, got {}",
+            target
+        )));
     }
 
-    let mut client = client.unwrap();
+    let mut flags = mode.flag().to_string();
+    match exact {
+        Some(true) => flags.push_str("+exact"),
+        Some(false) => flags.push_str("+keyframes"),
+        None => {}
+    }
+    pipe::command(json!(["seek", target, flags])).await?;
+    Ok(())
+}
+
+// Label of the filter `toggle_normalization` adds to `af`.
+const NORMALIZE_LABEL: &str = "normalize";
 
-    let volume = pipe::get_property(&mut client, "volume").await.as_f64();
-    if volume.is_none() {
-        tracing::debug!("Error getting volume");
-        return;
+/// Speeds `cycle_speed` steps through.
+pub const SPEED_PRESETS: [f64; 7] = [0.5, 0.75, 0.9, 1.0, 1.25, 1.5, 2.0];
+
+pub async fn set_speed(speed: f64) -> Result<(), Error> {
+    if !(0.01..=100.0).contains(&speed) {
+        return Err(Error::BadArgument(format!(
+            "Speed must be between 0.01 and 100, got {}",
+            speed
+        )));
     }
-    let volume = volume.unwrap().add(2.0f64);
+    pipe::set_property("speed", json!(speed)).await
+}
 
-    pipe::set_property(&mut client, "volume", volume.to_string().as_str()).await;
+pub async fn adjust_speed(delta: f64) -> Result<(), Error> {
+    pipe::command(json!(["add", "speed", delta])).await?;
+    Ok(())
 }
 
-pub async fn volume_down() {
-    #[cfg(windows)]
-    let client = pipe::get_client();
-    #[cfg(unix)]
-    let client = pipe::get_client().await;
+/// Moves to the next of `SPEED_PRESETS`, wrapping around after the last.
+pub async fn cycle_speed() -> Result<(), Error> {
+    let mut command = vec![json!("cycle-values"), json!("speed")];
+    command.extend(SPEED_PRESETS.iter().map(|speed| json!(speed.to_string())));
+    pipe::command(Value::Array(command)).await?;
+    Ok(())
+}
+
+/// Keeps the pitch when playing faster or slower, on by default in mpv.
+pub async fn toggle_pitch_correction() -> Result<(), Error> {
+    pipe::command(json!(["cycle", "audio-pitch-correction"])).await?;
+    Ok(())
+}
 
-    if client.is_err() {
-        tracing::debug!("Error getting client: {:?}", client.err().unwrap());
-        return;
+/// Sets the loop points in seconds, `None` leaves a point unset.
+pub async fn set_ab_loop(a: Option<f64>, b: Option<f64>) -> Result<(), Error> {
+    if let (Some(a), Some(b)) = (a, b) {
+        if a >= b {
+            return Err(Error::BadArgument(format!(
+                "Loop start {} must be before its end {}",
+                a, b
+            )));
+        }
     }
-    let mut client = client.unwrap();
+    pipe::set_property("ab-loop-a", a.map_or(json!("no"), |a| json!(a))).await?;
+    pipe::set_property("ab-loop-b", b.map_or(json!("no"), |b| json!(b))).await
+}
+
+pub async fn clear_ab_loop() -> Result<(), Error> {
+    set_ab_loop(None, None).await
+}
+
+/// Like mpv's `l` key: sets A at the current position, then B, then clears.
+pub async fn cycle_ab_loop() -> Result<(), Error> {
+    pipe::command(json!(["ab-loop"])).await?;
+    Ok(())
+}
+
+pub async fn chapter_next() -> Result<(), Error> {
+    pipe::command(json!(["add", "chapter", 1])).await?;
+    Ok(())
+}
+
+pub async fn chapter_previous() -> Result<(), Error> {
+    pipe::command(json!(["add", "chapter", -1])).await?;
+    Ok(())
+}
 
-    let volume = pipe::get_property(&mut client, "volume").await.as_f64();
-    if volume.is_none() {
-        tracing::debug!("Error getting volume");
-        return;
+pub async fn set_chapter(index: i64) -> Result<(), Error> {
+    if index < 0 {
+        return Err(Error::BadArgument(format!(
+            "Chapter must not be negative, got {}",
+            index
+        )));
     }
-    let volume = volume.unwrap().sub(2.0f64);
+    pipe::set_property("chapter", json!(index)).await
+}
+
+/// URL schemes passed to mpv as they are. Others like `edl://` or `lavf://`
+/// can open arbitrary local files and would get around the media roots.
+const STREAM_SCHEMES: &[&str] = &["http", "https", "rtsp", "ytdl"];
+
+/// Opens a URL or a file under one of the media roots.
+pub async fn loadfile(target: &str, mode: LoadMode) -> Result<(), Error> {
+    let target = load_target(target)?;
+    pipe::command(json!(["loadfile", target, mode.flag()])).await?;
+    Ok(())
+}
+
+fn load_target(target: &str) -> Result<String, Error> {
+    Ok(match target.split_once("://") {
+        Some((scheme, path)) if scheme.eq_ignore_ascii_case("file") => {
+            library::resolve(path)?.to_string_lossy().to_string()
+        }
+        Some((scheme, _)) if STREAM_SCHEMES.contains(&scheme.to_lowercase().as_str()) => {
+            target.to_string()
+        }
+        Some((scheme, _)) => {
+            return Err(Error::BadArgument(format!(
+                "Unsupported URL scheme: {}",
+                scheme
+            )))
+        }
+        None => library::resolve(target)?.to_string_lossy().to_string(),
+    })
+}
+
+pub async fn get_playlist() -> Result<Vec<PlaylistEntry>, Error> {
+    let playlist = pipe::get_property("playlist").await?;
+    serde_json::from_value(playlist).map_err(|e| Error::Protocol(e.to_string()))
+}
+
+pub async fn playlist_next() -> Result<(), Error> {
+    pipe::command(json!(["playlist-next"])).await?;
+    Ok(())
+}
 
-    pipe::set_property(&mut client, "volume", volume.to_string().as_str()).await;
+pub async fn playlist_previous() -> Result<(), Error> {
+    pipe::command(json!(["playlist-prev"])).await?;
+    Ok(())
 }
 
-pub async fn get_status() -> Result<Status, Box<dyn std::error::Error>> {
-    #[cfg(windows)]
-    let client = pipe::get_client();
-    #[cfg(unix)]
-    let client = pipe::get_client().await;
+pub async fn playlist_play(index: i64) -> Result<(), Error> {
+    pipe::set_property("playlist-pos", json!(index)).await
+}
 
-    if client.is_err() {
-        return Err(format!("Error getting client: {:?}", client.err().unwrap()).into());
+pub async fn playlist_remove(index: i64) -> Result<(), Error> {
+    pipe::command(json!(["playlist-remove", index])).await?;
+    Ok(())
+}
+
+/// Moves the entry at `from` so it ends up before the entry at `to`.
+pub async fn playlist_move(from: i64, to: i64) -> Result<(), Error> {
+    pipe::command(json!(["playlist-move", from, to])).await?;
+    Ok(())
+}
+
+pub async fn playlist_shuffle() -> Result<(), Error> {
+    pipe::command(json!(["playlist-shuffle"])).await?;
+    Ok(())
+}
+
+pub async fn playlist_unshuffle() -> Result<(), Error> {
+    pipe::command(json!(["playlist-unshuffle"])).await?;
+    Ok(())
+}
+
+/// Removes everything but the file that is playing.
+pub async fn playlist_clear() -> Result<(), Error> {
+    pipe::command(json!(["playlist-clear"])).await?;
+    Ok(())
+}
+
+pub async fn toggle_loop_file() -> Result<(), Error> {
+    pipe::command(json!(["cycle-values", "loop-file", "inf", "no"])).await?;
+    Ok(())
+}
+
+pub async fn toggle_loop_playlist() -> Result<(), Error> {
+    pipe::command(json!(["cycle-values", "loop-playlist", "inf", "no"])).await?;
+    Ok(())
+}
+
+/// Every known instance with whether it's reachable right now.
+pub async fn list_instances() -> Vec<InstanceStatus> {
+    let default = instances::default_name();
+    let mut list = vec![];
+    for instance in instances::instances() {
+        let running = scope(instance.name.clone(), async {
+            observer::status().is_some()
+        })
+        .await;
+        list.push(InstanceStatus {
+            default: instance.name == default,
+            instance,
+            running,
+        });
     }
+    list
+}
 
-    let mut client = client.unwrap();
+pub async fn get_status() -> Result<Status, Error> {
+    Ok(observer::status().unwrap_or(Status {
+        running: false,
+        ..Default::default()
+    }))
+}
 
-    let paused = pipe::get_property(&mut client, "pause")
-        .await
+async fn get_bool(property: &str) -> Result<bool, Error> {
+    let value = pipe::get_property(property).await?;
+    value
         .as_bool()
-        .unwrap_or(true);
-
-    let volume = pipe::get_property(&mut client, "volume")
-        .await
-        .as_f64()
-        .unwrap_or(0.0);
+        .ok_or_else(|| Error::Protocol(format!("{} is not a bool: {}", property, value)))
+}
 
-    let position = pipe::get_property(&mut client, "time-pos")
-        .await
+async fn get_f64(property: &str) -> Result<f64, Error> {
+    let value = pipe::get_property(property).await?;
+    value
         .as_f64()
-        .unwrap_or(0.0);
+        .ok_or_else(|| Error::Protocol(format!("{} is not a number: {}", property, value)))
+}
 
-    let length = pipe::get_property(&mut client, "duration")
-        .await
-        .as_f64()
-        .unwrap_or(0.0);
+fn has_normalize_filter(filters: &Value) -> bool {
+    filters
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|filter| filter["label"] == NORMALIZE_LABEL)
+}
 
-    let title = pipe::get_property(&mut client, "media-title")
-        .await
-        .to_string();
+/// `loop-file` and `loop-playlist` are either `false`/`"no"` or a count.
+fn looping(value: &Value) -> bool {
+    !(value.is_null() || value == false || value == "no")
+}
 
-    let file = pipe::get_property(&mut client, "filename")
-        .await
-        .to_string();
+fn chapters(chapters: &Value) -> Vec<Chapter> {
+    chapters
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|chapter| Chapter {
+            title: chapter["title"].as_str().map(str::to_string),
+            time: chapter["time"].as_f64().unwrap_or(0.0),
+        })
+        .collect()
+}
 
-    let subtitle = pipe::get_property(&mut client, "sid").await.to_string();
+fn tracks(tracks: &Value, kind: &str) -> Vec<Track> {
+    let mut result: Vec<Track> = vec![];
+    for track in tracks.as_array().into_iter().flatten() {
+        if track["type"] == kind {
+            let text = |key: &str| track[key].as_str().map(str::to_string);
+            result.push(Track {
+                id: track["id"].as_i64().unwrap_or_default(),
+                lang: text("lang"),
+                title: text("title"),
+                codec: text("codec"),
+                channels: track["demux-channel-count"].as_i64(),
+            })
+        }
+    }
 
-    let track_list_count = pipe::get_property(&mut client, "track-list/count")
-        .await
-        .as_i64()
-        .unwrap();
+    result
+}
 
+fn subtitles(tracks: &Value) -> Vec<Subtitle> {
     let mut subtitles: Vec<Subtitle> = vec![];
-    for i in 0..track_list_count {
-        let track_type = pipe::get_property(&mut client, format!("track-list/{}/type", i).as_str())
-            .await
-            .to_string();
-        if track_type == "\"sub\"" {
-            let id = pipe::get_property(&mut client, format!("track-list/{}/id", i).as_str())
-                .await
-                .as_i64()
-                .unwrap();
-            let lang = pipe::get_property(&mut client, format!("track-list/{}/lang", i).as_str())
-                .await
-                .to_string();
-            let title = pipe::get_property(&mut client, format!("track-list/{}/title", i).as_str())
-                .await
-                .to_string();
+    for track in tracks.as_array().into_iter().flatten() {
+        if track["type"] == "sub" {
+            let id = track["id"].as_i64().unwrap_or_default();
+            let lang = track["lang"].to_string();
+            let title = track["title"].to_string();
 
             let mut result = lang.clone();
 
             if title != "null" {
                 result = format!("{} - {}", lang, title);
             }
-            result = result.replace("\"", "");
+            result = result.replace('"', "");
 
-            subtitles.push(Subtitle { id, title: result })
+            subtitles.push(Subtitle {
+                id,
+                title: result,
+                loaded: true,
+            })
         }
     }
 
-    Ok(Status {
-        paused,
-        volume,
-        position,
-        length,
-        title,
-        file,
-        subtitle,
-        subtitles,
-    })
+    subtitles.extend(external::not_loaded(tracks));
+    subtitles
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct Subtitle {
-    pub id: i64,
-    pub title: String,
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SeekMode {
+    Relative,
+    Absolute,
+    RelativePercent,
+    AbsolutePercent,
+}
+
+impl SeekMode {
+    fn flag(self) -> &'static str {
+        match self {
+            SeekMode::Relative => "relative",
+            SeekMode::Absolute => "absolute",
+            SeekMode::RelativePercent => "relative-percent",
+            SeekMode::AbsolutePercent => "absolute-percent",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct VolumeLimits {
+    pub step: f64,
+    pub min: f64,
+    pub max: f64,
+    pub night_mode: bool,
+}
+
+impl VolumeLimits {
+    pub fn clamp(&self, volume: f64) -> f64 {
+        // `max` wins if the settings have them the wrong way around.
+        volume.max(self.min).min(self.max)
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct InstanceStatus {
+    #[serde(flatten)]
+    pub instance: Instance,
+    /// Used by the `/mpv/*` routes.
+    pub default: bool,
+    pub running: bool,
+}
+
+/// Where `loadfile` puts the new entry.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LoadMode {
+    /// Stops what's playing and plays the new file.
+    Replace,
+    Append,
+    /// Appends, and starts playing if nothing is.
+    AppendPlay,
+    /// Plays the new file after the current one.
+    InsertNext,
+}
+
+impl LoadMode {
+    fn flag(self) -> &'static str {
+        match self {
+            LoadMode::Replace => "replace",
+            LoadMode::Append => "append",
+            LoadMode::AppendPlay => "append-play",
+            LoadMode::InsertNext => "insert-next",
+        }
+    }
 }
 
+/// Player events derived from mpv's event stream, each carrying the part of
+/// [`Status`] it affects.
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct Status {
-    pub paused: bool,
-    pub volume: f64,
-    pub position: f64,
-    pub length: f64,
-    pub title: String,
-    pub file: String,
-    pub subtitle: String,
-    pub subtitles: Vec<Subtitle>,
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum PlayerEvent {
+    PlaybackStarted {
+        title: String,
+        file: String,
+        length: f64,
+    },
+    Paused {
+        position: f64,
+    },
+    Resumed {
+        position: f64,
+    },
+    EndFile {
+        reason: String,
+        position: f64,
+    },
+    VolumeChanged {
+        volume: f64,
+    },
+    TrackChanged {
+        subtitle: Option<i64>,
+        subtitles: Vec<Subtitle>,
+        secondary_subtitle: Option<i64>,
+        audio: Option<i64>,
+        video: Option<i64>,
+    },
+}
+
+impl PlayerEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlayerEvent::PlaybackStarted { .. } => "playback-started",
+            PlayerEvent::Paused { .. } => "paused",
+            PlayerEvent::Resumed { .. } => "resumed",
+            PlayerEvent::EndFile { .. } => "end-file",
+            PlayerEvent::VolumeChanged { .. } => "volume-changed",
+            PlayerEvent::TrackChanged { .. } => "track-changed",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streams_pass_through() {
+        for url in [
+            "https://example.com/video.mp4",
+            "HTTP://example.com/live.m3u8",
+            "rtsp://camera.local/stream",
+            "ytdl://ytsearch:trailer",
+        ] {
+            assert_eq!(load_target(url).unwrap(), url);
+        }
+    }
+
+    #[test]
+    fn other_schemes_are_rejected() {
+        for url in [
+            "edl://%10%/etc/passwd",
+            "slice://0-100@/etc/passwd",
+            "fd://0",
+            "av://lavfi:movie=/etc/passwd",
+            "lavf:///etc/passwd",
+            "memory://data",
+        ] {
+            assert!(
+                matches!(load_target(url), Err(Error::BadArgument(_))),
+                "{}",
+                url
+            );
+        }
+    }
 }
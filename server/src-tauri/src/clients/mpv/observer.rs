@@ -0,0 +1,334 @@
+use std::collections::{HashMap, HashSet};
+
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use tokio::sync::{broadcast, broadcast::error::RecvError, watch};
+
+use super::pipe::{self, Connection};
+use super::{
+    chapters, external, has_normalize_filter, instances, looping, process, subtitles, tracks,
+    volume_limits, PlayerEvent, Status, VolumeLimits,
+};
+use crate::settings::{self, load_settings};
+
+const OBSERVED: [&str; 28] = [
+    "pause",
+    "volume",
+    "time-pos",
+    "duration",
+    "media-title",
+    "filename",
+    "path",
+    "sid",
+    "secondary-sid",
+    "sub-delay",
+    "sub-scale",
+    "sub-pos",
+    "sub-visibility",
+    "aid",
+    "vid",
+    "track-list",
+    "chapter",
+    "chapter-list",
+    "playlist",
+    "playlist-pos",
+    "loop-file",
+    "loop-playlist",
+    "speed",
+    "audio-pitch-correction",
+    "ab-loop-a",
+    "ab-loop-b",
+    "mute",
+    "af",
+];
+
+/// What the observer keeps for itself beyond the status it publishes.
+#[derive(Default)]
+struct State {
+    // mpv answers every observe_property with the current value, which
+    // shouldn't be reported as a change.
+    seen: HashSet<String>,
+    // Raw `track-list`, needed again when the subtitles found on disk change.
+    track_list: Value,
+    // Read once per connection and again after settings are written, along
+    // with `settings::writes` at the time.
+    limits: Option<(u64, VolumeLimits)>,
+}
+
+impl State {
+    fn volume_limits(&mut self) -> VolumeLimits {
+        let writes = settings::writes();
+        match self.limits {
+            Some((read_at, limits)) if read_at == writes => limits,
+            _ => {
+                let limits = volume_limits();
+                self.limits = Some((writes, limits));
+                limits
+            }
+        }
+    }
+}
+
+lazy_static! {
+    // Per instance, `None` while it isn't reachable.
+    static ref STATUS: Mutex<HashMap<String, watch::Sender<Option<Status>>>> =
+        Mutex::new(HashMap::new());
+    static ref EVENTS: Mutex<HashMap<String, broadcast::Sender<PlayerEvent>>> =
+        Mutex::new(HashMap::new());
+    // Instances with an observer running.
+    static ref OBSERVING: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+fn status_sender() -> watch::Sender<Option<Status>> {
+    STATUS
+        .lock()
+        .entry(instances::current())
+        .or_insert_with(|| watch::Sender::new(None))
+        .clone()
+}
+
+fn events_sender() -> broadcast::Sender<PlayerEvent> {
+    EVENTS
+        .lock()
+        .entry(instances::current())
+        .or_insert_with(|| broadcast::channel(16).0)
+        .clone()
+}
+
+/// Latest snapshot built from `property-change` events.
+pub fn status() -> Option<Status> {
+    status_sender().borrow().clone()
+}
+
+/// Receiver that is notified every time the cached status changes.
+pub fn watch_status() -> watch::Receiver<Option<Status>> {
+    status_sender().subscribe()
+}
+
+/// Receiver for the typed events derived from mpv's event stream.
+pub fn subscribe_events() -> broadcast::Receiver<PlayerEvent> {
+    events_sender().subscribe()
+}
+
+/// Keeps every mpv instance observed, refreshing the instance list and picking
+/// up new ones every second.
+pub async fn observe() {
+    if load_settings().mpv.and_then(|mpv| mpv.launch) == Some(true) {
+        let launch = process::start();
+        if let Err(e) = instances::scope(instances::DEFAULT.to_string(), launch).await {
+            tracing::debug!("Error launching mpv: {}", e);
+        }
+    }
+
+    loop {
+        if let Err(e) = tokio::task::spawn_blocking(instances::refresh).await {
+            tracing::debug!("Error refreshing mpv instances: {:?}", e);
+        }
+        for instance in instances::instances() {
+            if OBSERVING.lock().insert(instance.name.clone()) {
+                tokio::spawn(instances::scope(instance.name, observe_instance()));
+            }
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+}
+
+/// Keeps a connection to the current instance open and its status cache up to
+/// date, retrying every second while it isn't running. Stops once the
+/// instance is gone from settings or its socket disappeared.
+async fn observe_instance() {
+    let name = instances::current();
+    loop {
+        if let Ok(connection) = pipe::get_connection().await {
+            watch_connection(connection).await;
+            tracing::debug!("Stopped observing mpv {}", name);
+        }
+
+        // Only a real change wakes the watchers, not every retry while mpv is down.
+        status_sender().send_if_modified(|status| status.take().is_some());
+        if instances::pipe(&name).is_none() {
+            OBSERVING.lock().remove(&name);
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+}
+
+async fn watch_connection(connection: Connection) {
+    // Subscribe before observing so the initial values mpv sends back aren't missed.
+    let mut events = connection.subscribe();
+
+    status_sender().send_replace(Some(Status {
+        title: Value::Null.to_string(),
+        file: Value::Null.to_string(),
+        ..Default::default()
+    }));
+
+    for (id, property) in OBSERVED.iter().enumerate() {
+        let command = json!({ "command": ["observe_property", id + 1, property] });
+        if let Err(e) = connection.send(command).await {
+            tracing::debug!("Error observing {}: {:?}", property, e);
+            return;
+        }
+    }
+
+    let mut state = State::default();
+
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => handle(&event, &mut state).await,
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::debug!("Skipped {} mpv events", skipped);
+                }
+                Err(RecvError::Closed) => return,
+            },
+            _ = connection.closed() => return,
+        }
+    }
+}
+
+async fn handle(event: &Value, state: &mut State) {
+    match event["event"].as_str() {
+        Some("property-change") => property_change(event, state).await,
+        Some("file-loaded") => {
+            let title = pipe::get_property("media-title").await.unwrap_or_default();
+            let file = pipe::get_property("filename").await.unwrap_or_default();
+            let length = pipe::get_property("duration").await.unwrap_or_default();
+            emit(PlayerEvent::PlaybackStarted {
+                title: title.to_string(),
+                file: file.to_string(),
+                length: length.as_f64().unwrap_or(0.0),
+            });
+        }
+        Some("end-file") => {
+            let position = status().map(|status| status.position).unwrap_or(0.0);
+            emit(PlayerEvent::EndFile {
+                reason: event["reason"].as_str().unwrap_or("unknown").to_string(),
+                position,
+            });
+        }
+        _ => {}
+    }
+}
+
+async fn property_change(event: &Value, state: &mut State) {
+    let Some(name) = event["name"].as_str() else {
+        return;
+    };
+    let data = &event["data"];
+    let initial = state.seen.insert(name.to_string());
+
+    // Looking through the directory happens on the blocking pool and before
+    // touching the status, so neither the runtime nor watchers wait on the disk.
+    match name {
+        "path" => external::discover(data.as_str().map(str::to_string)).await,
+        "track-list" => state.track_list = data.clone(),
+        _ => {}
+    }
+    let mut changed = None;
+
+    status_sender().send_if_modified(|status| {
+        let Some(status) = status.as_mut() else {
+            return false;
+        };
+
+        match name {
+            "pause" => {
+                let paused = data.as_bool().unwrap_or(true);
+                if paused != status.paused {
+                    changed = Some(if paused {
+                        PlayerEvent::Paused {
+                            position: status.position,
+                        }
+                    } else {
+                        PlayerEvent::Resumed {
+                            position: status.position,
+                        }
+                    });
+                }
+                status.paused = paused;
+            }
+            "volume" => {
+                status.volume = data.as_f64().unwrap_or(0.0);
+                changed = Some(PlayerEvent::VolumeChanged {
+                    volume: status.volume,
+                });
+            }
+            "mute" => status.muted = data.as_bool().unwrap_or(false),
+            "af" => status.normalize = has_normalize_filter(data),
+            "time-pos" => status.position = data.as_f64().unwrap_or(0.0),
+            "duration" => status.length = data.as_f64().unwrap_or(0.0),
+            "media-title" => status.title = data.to_string(),
+            "filename" => status.file = data.to_string(),
+            "chapter" => status.chapter = data.as_i64(),
+            "chapter-list" => status.chapters = chapters(data),
+            "playlist" => {
+                status.playlist = serde_json::from_value(data.clone()).unwrap_or_default()
+            }
+            // -1 when nothing is playing.
+            "playlist-pos" => status.playlist_position = data.as_i64().filter(|pos| *pos >= 0),
+            "loop-file" => status.loop_file = looping(data),
+            "loop-playlist" => status.loop_playlist = looping(data),
+            "speed" => status.speed = data.as_f64().unwrap_or(1.0),
+            "audio-pitch-correction" => status.pitch_correction = data.as_bool().unwrap_or(true),
+            // `"no"` while unset.
+            "ab-loop-a" => status.ab_loop_a = data.as_f64(),
+            "ab-loop-b" => status.ab_loop_b = data.as_f64(),
+            "sub-delay" => status.subtitle_delay = data.as_f64().unwrap_or(0.0),
+            "sub-scale" => status.subtitle_scale = data.as_f64().unwrap_or(1.0),
+            "sub-pos" => status.subtitle_position = data.as_f64().unwrap_or(100.0),
+            "sub-visibility" => status.subtitle_visible = data.as_bool().unwrap_or(true),
+            "sid" | "secondary-sid" | "aid" | "vid" | "track-list" | "path" => {
+                match name {
+                    // `false` when no track is selected.
+                    "sid" => status.subtitle = data.as_i64(),
+                    "secondary-sid" => status.secondary_subtitle = data.as_i64(),
+                    "aid" => status.audio = data.as_i64(),
+                    "vid" => status.video = data.as_i64(),
+                    "path" => status.subtitles = subtitles(&state.track_list),
+                    _ => {
+                        status.subtitles = subtitles(data);
+                        status.audio_tracks = tracks(data, "audio");
+                        status.video_tracks = tracks(data, "video");
+                    }
+                }
+                changed = Some(PlayerEvent::TrackChanged {
+                    subtitle: status.subtitle,
+                    subtitles: status.subtitles.clone(),
+                    secondary_subtitle: status.secondary_subtitle,
+                    audio: status.audio,
+                    video: status.video,
+                });
+            }
+            _ => return false,
+        }
+
+        true
+    });
+
+    if let Some(event) = changed.filter(|_| !initial) {
+        emit(event);
+    }
+
+    // Keys pressed in mpv itself don't go through `set_volume`, hold the
+    // limits here as well.
+    if name == "volume" {
+        if let Some(volume) = data.as_f64() {
+            let limited = state.volume_limits().clamp(volume);
+            if limited != volume {
+                let name = instances::current();
+                tokio::spawn(instances::scope(name, async move {
+                    let _ = pipe::set_property("volume", json!(limited)).await;
+                }));
+            }
+        }
+    }
+}
+
+fn emit(event: PlayerEvent) {
+    tracing::debug!("mpv event: {}", event.name());
+    // Nobody listening is fine, events are best effort.
+    let _ = events_sender().send(event);
+}
@@ -1,108 +1,354 @@
-use crate::settings::load_settings;
+use super::{instances, Error};
+use lazy_static::lazy_static;
 use serde_json::{from_str, json, Value};
-use std::str::from_utf8;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{broadcast, mpsc, oneshot, Mutex};
 
 #[cfg(windows)]
 use tokio::net::windows::named_pipe::{ClientOptions, NamedPipeClient};
 #[cfg(unix)]
 use tokio::net::UnixStream;
 
-#[cfg(windows)]
-pub fn get_client() -> Result<NamedPipeClient, std::io::Error> {
-    let mpv = load_settings().mpv.expect("No mpv in settings");
-    ClientOptions::new().open(mpv.pipe.as_str())
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    // Keyed by instance name.
+    static ref CONNECTIONS: Mutex<HashMap<String, Connection>> = Mutex::new(HashMap::new());
 }
 
-#[cfg(windows)]
-pub async fn get_property(client: &mut NamedPipeClient, property: &str) -> serde_json::Value {
-    let msg = json!({"command": ["get_property", property]});
-    send_msg(client, msg).await;
+enum Request {
+    Send {
+        id: u64,
+        command: Value,
+        reply: oneshot::Sender<Value>,
+    },
+    /// The caller gave up waiting, forget about its reply.
+    Cancel(u64),
+}
 
-    let mut buffer = [0; 1024];
-    let n = client
-        .read(&mut buffer)
-        .await
-        .map_err(|e| e.to_string())
-        .unwrap();
+/// Handle to the long-lived IPC connection. The socket itself is owned by a
+/// background task which tags every command with a `request_id` and routes
+/// mpv's replies back to the caller that sent it.
+#[derive(Clone)]
+pub struct Connection {
+    requests: mpsc::UnboundedSender<Request>,
+    events: broadcast::Sender<Value>,
+    next_id: Arc<AtomicU64>,
+}
 
-    let response = from_utf8(&buffer[..n]).unwrap();
-    let response_json: Value = from_str(response).unwrap();
+impl Connection {
+    fn spawn<S>(stream: S) -> Connection
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (requests, receiver) = mpsc::unbounded_channel();
+        let (events, _) = broadcast::channel(64);
+        tokio::spawn(run(stream, receiver, events.clone()));
 
-    response_json["data"].clone()
-}
-#[cfg(windows)]
-pub async fn set_bool_property(client: &mut NamedPipeClient, property: &str, value: bool) {
-    let msg = json!({"command": ["set_property", property, value]});
-    send_msg(client, msg).await;
-}
-#[cfg(windows)]
-pub async fn set_property(client: &mut NamedPipeClient, property: &str, value: &str) {
-    let msg = json!({"command": ["set_property_string", property, value]});
-    send_msg(client, msg).await;
+        Connection {
+            requests,
+            events,
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// Sends a raw command object and waits for the reply with the matching `request_id`.
+    pub async fn send(&self, command: Value) -> Result<Value, Error> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (reply, response) = oneshot::channel();
+        self.requests
+            .send(Request::Send { id, command, reply })
+            .map_err(|_| Error::NotRunning)?;
+
+        match tokio::time::timeout(TIMEOUT, response).await {
+            Ok(Ok(reply)) => Ok(reply),
+            // The connection task dropped the request, mpv went away.
+            Ok(Err(_)) => Err(Error::NotRunning),
+            Err(_) => {
+                let _ = self.requests.send(Request::Cancel(id));
+                Err(Error::Timeout)
+            }
+        }
+    }
+
+    /// Asynchronous `event` messages mpv emits between replies.
+    pub fn subscribe(&self) -> broadcast::Receiver<Value> {
+        self.events.subscribe()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.requests.is_closed()
+    }
+
+    /// Resolves once the connection task has exited.
+    pub async fn closed(&self) {
+        self.requests.closed().await
+    }
 }
+
 #[cfg(windows)]
-pub async fn send_msg(client: &mut NamedPipeClient, msg: Value) {
-    client
-        .write_all(msg.to_string().as_bytes())
-        .await
-        .map_err(|e| e.to_string())
-        .unwrap();
-
-    client
-        .write_all(b"\n")
-        .await
-        .map_err(|e| e.to_string())
-        .unwrap();
+async fn open(pipe: &str) -> Result<NamedPipeClient, io::Error> {
+    ClientOptions::new().open(pipe)
 }
 
 #[cfg(unix)]
-pub async fn send_msg(client: &mut UnixStream, msg: Value) {
-    client
-        .write_all(msg.to_string().as_bytes())
-        .await
-        .map_err(|e| e.to_string())
-        .unwrap();
-
-    client
-        .write_all(b"\n")
-        .await
-        .map_err(|e| e.to_string())
-        .unwrap();
+async fn open(pipe: &str) -> Result<UnixStream, io::Error> {
+    UnixStream::connect(pipe).await
 }
 
-#[cfg(unix)]
-pub async fn get_client() -> Result<UnixStream, std::io::Error> {
-    let mpv = load_settings().mpv.expect("No mpv in settings");
-    UnixStream::connect(mpv.pipe.as_str()).await
+/// Returns the shared connection to the current instance, reconnecting if mpv
+/// went away since the last call.
+pub async fn get_connection() -> Result<Connection, Error> {
+    let name = instances::current();
+    let mut connections = CONNECTIONS.lock().await;
+    if let Some(existing) = connections.get(&name) {
+        if !existing.is_closed() {
+            return Ok(existing.clone());
+        }
+    }
+
+    let Some(path) = instances::pipe(&name) else {
+        tracing::debug!("No mpv instance {} in settings", name);
+        return Err(Error::NotRunning);
+    };
+    let stream = open(&path).await.map_err(|e| {
+        // Connection attempts are retried constantly while mpv is closed, keep this quiet.
+        tracing::trace!("Error connecting to mpv: {:?}", e);
+        Error::NotRunning
+    })?;
+    tracing::debug!("Connected to mpv {} at {}", name, path);
+
+    let new = Connection::spawn(stream);
+    connections.insert(name, new.clone());
+    Ok(new)
 }
 
-#[cfg(unix)]
-pub async fn get_property(client: &mut UnixStream, property: &str) -> Value {
-    let msg = json!({"command": ["get_property", property]});
-    send_msg(client, msg).await;
+async fn run<S>(
+    stream: S,
+    mut requests: mpsc::UnboundedReceiver<Request>,
+    events: broadcast::Sender<Value>,
+) where
+    S: AsyncRead + AsyncWrite,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+    let mut pending: HashMap<u64, oneshot::Sender<Value>> = HashMap::new();
+
+    loop {
+        tokio::select! {
+            request = requests.recv() => {
+                let (id, mut command, reply) = match request {
+                    Some(Request::Send { id, command, reply }) => (id, command, reply),
+                    Some(Request::Cancel(id)) => {
+                        pending.remove(&id);
+                        continue;
+                    }
+                    None => break,
+                };
 
-    let mut buffer = [0; 1024];
-    let n = client
-        .read(&mut buffer)
-        .await
-        .map_err(|e| e.to_string())
-        .unwrap();
+                command["request_id"] = json!(id);
+                pending.insert(id, reply);
 
-    let response = from_utf8(&buffer[..n]).unwrap();
-    let response_json: Value = from_str(response).unwrap();
+                let mut line = command.to_string();
+                line.push('\n');
+                if let Err(e) = writer.write_all(line.as_bytes()).await {
+                    tracing::debug!("Error writing to mpv: {:?}", e);
+                    break;
+                }
+            }
+            line = lines.next_line() => match line {
+                Ok(Some(line)) => dispatch(&line, &mut pending, &events),
+                Ok(None) => {
+                    tracing::debug!("mpv closed the connection");
+                    break;
+                }
+                Err(e) => {
+                    tracing::debug!("Error reading from mpv: {:?}", e);
+                    break;
+                }
+            },
+        }
+    }
 
-    response_json["data"].clone()
+    // Returning drops the receiver, which marks the handle as closed so the next
+    // caller reconnects, and drops `pending`, which fails everyone still waiting.
 }
 
-#[cfg(unix)]
-pub async fn set_bool_property(client: &mut UnixStream, property: &str, value: bool) {
-    let msg = json!({"command": ["set_property", property, value]});
-    send_msg(client, msg).await;
+fn dispatch(
+    line: &str,
+    pending: &mut HashMap<u64, oneshot::Sender<Value>>,
+    events: &broadcast::Sender<Value>,
+) {
+    let message: Value = match from_str(line) {
+        Ok(message) => message,
+        Err(e) => {
+            tracing::debug!("Invalid JSON from mpv: {:?} ({})", e, line);
+            return;
+        }
+    };
+
+    if message.get("event").is_some() {
+        // Nobody listening is fine, events are best effort.
+        let _ = events.send(message);
+        return;
+    }
+
+    match message.get("request_id").and_then(Value::as_u64) {
+        Some(id) => {
+            if let Some(reply) = pending.remove(&id) {
+                let _ = reply.send(message);
+            }
+        }
+        None => tracing::debug!("Unmatched message from mpv: {}", line),
+    }
 }
 
-#[cfg(unix)]
-pub async fn set_property(client: &mut UnixStream, property: &str, value: &str) {
-    let msg = json!({"command": ["set_property_string", property, value]});
-    send_msg(client, msg).await;
+/// Runs a command and returns its `data`, turning mpv's `error` field into an [`Error`].
+pub async fn command(args: Value) -> Result<Value, Error> {
+    let connection = get_connection().await?;
+    let reply = connection.send(json!({ "command": args })).await?;
+
+    match reply["error"].as_str() {
+        Some("success") => Ok(reply["data"].clone()),
+        Some("property unavailable") | Some("property not found") => {
+            let property = args[1].as_str().unwrap_or_default();
+            Err(Error::PropertyUnavailable(property.to_string()))
+        }
+        Some("invalid parameter") | Some("unsupported format for accessing property") => {
+            Err(Error::BadArgument(format!("{} rejected by mpv", args)))
+        }
+        Some(error) => Err(Error::Protocol(error.to_string())),
+        None => Err(Error::Protocol(format!(
+            "reply without error field: {}",
+            reply
+        ))),
+    }
+}
+
+pub async fn get_property(property: &str) -> Result<Value, Error> {
+    command(json!(["get_property", property])).await
+}
+
+pub async fn set_property(property: &str, value: Value) -> Result<(), Error> {
+    command(json!(["set_property", property, value])).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{duplex, DuplexStream, Lines, ReadHalf, WriteHalf};
+
+    type Mpv = (
+        Lines<BufReader<ReadHalf<DuplexStream>>>,
+        WriteHalf<DuplexStream>,
+    );
+
+    fn connect() -> (Connection, Mpv) {
+        let (client, server) = duplex(4096);
+        let (reader, writer) = tokio::io::split(server);
+        (
+            Connection::spawn(client),
+            (BufReader::new(reader).lines(), writer),
+        )
+    }
+
+    async fn next_request(mpv: &mut Mpv) -> Value {
+        let line = mpv.0.next_line().await.unwrap().unwrap();
+        from_str(&line).unwrap()
+    }
+
+    #[tokio::test]
+    async fn routes_replies_by_request_id() {
+        let (connection, mut mpv) = connect();
+        let mut events = connection.subscribe();
+
+        let first = tokio::spawn({
+            let connection = connection.clone();
+            async move { connection.send(json!({ "command": ["first"] })).await }
+        });
+        let first_request = next_request(&mut mpv).await;
+        let second = tokio::spawn({
+            let connection = connection.clone();
+            async move { connection.send(json!({ "command": ["second"] })).await }
+        });
+        let second_request = next_request(&mut mpv).await;
+        assert_eq!(first_request["command"], json!(["first"]));
+        assert_eq!(second_request["command"], json!(["second"]));
+
+        // An event, the second reply before the first, and the first reply
+        // split across two writes.
+        let first_reply = json!({
+            "error": "success",
+            "data": 1,
+            "request_id": first_request["request_id"],
+        })
+        .to_string();
+        let (start, end) = first_reply.split_at(10);
+        let output = format!(
+            "{}\n{}\n{}",
+            json!({ "event": "pause" }),
+            json!({
+                "error": "success",
+                "data": 2,
+                "request_id": second_request["request_id"],
+            }),
+            start,
+        );
+        mpv.1.write_all(output.as_bytes()).await.unwrap();
+        assert_eq!(second.await.unwrap().unwrap()["data"], 2);
+        mpv.1
+            .write_all(format!("{}\n", end).as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(first.await.unwrap().unwrap()["data"], 1);
+
+        assert_eq!(events.recv().await.unwrap()["event"], "pause");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timed_out_request_does_not_block_the_next() {
+        let (connection, mut mpv) = connect();
+
+        let result = connection.send(json!({ "command": ["stuck"] })).await;
+        assert!(matches!(result, Err(Error::Timeout)));
+        let stuck = next_request(&mut mpv).await;
+
+        let next = tokio::spawn({
+            let connection = connection.clone();
+            async move { connection.send(json!({ "command": ["next"] })).await }
+        });
+        let request = next_request(&mut mpv).await;
+        assert_ne!(request["request_id"], stuck["request_id"]);
+
+        // The late reply is dropped, only the live request gets an answer.
+        let output = format!(
+            "{}\n{}\n",
+            json!({ "error": "success", "data": "late", "request_id": stuck["request_id"] }),
+            json!({ "error": "success", "data": "next", "request_id": request["request_id"] }),
+        );
+        mpv.1.write_all(output.as_bytes()).await.unwrap();
+        assert_eq!(next.await.unwrap().unwrap()["data"], "next");
+    }
+
+    #[tokio::test]
+    async fn closed_stream_fails_pending_requests() {
+        let (connection, mut mpv) = connect();
+
+        let pending = tokio::spawn({
+            let connection = connection.clone();
+            async move { connection.send(json!({ "command": ["never"] })).await }
+        });
+        next_request(&mut mpv).await;
+        drop(mpv);
+
+        assert!(matches!(pending.await.unwrap(), Err(Error::NotRunning)));
+        connection.closed().await;
+        assert!(connection.is_closed());
+    }
 }
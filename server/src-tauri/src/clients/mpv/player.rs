@@ -0,0 +1,66 @@
+use async_trait::async_trait;
+
+use super::{Error, PlaylistEntry, Status};
+use crate::clients::MediaPlayer;
+
+/// The local mpv instance reached through `MpvSettings::pipe`.
+pub struct Mpv;
+
+#[async_trait]
+impl MediaPlayer for Mpv {
+    async fn toggle_pause(&self) -> Result<(), Error> {
+        super::toggle_pause().await
+    }
+
+    async fn seek(&self, offset: f64) -> Result<(), Error> {
+        super::seek(offset).await
+    }
+
+    async fn seek_to(&self, position: f64) -> Result<(), Error> {
+        super::seek_to(position).await
+    }
+
+    async fn set_volume(&self, volume: i16) -> Result<(), Error> {
+        super::set_volume(volume).await
+    }
+
+    async fn volume_up(&self) -> Result<(), Error> {
+        super::volume_up().await
+    }
+
+    async fn volume_down(&self) -> Result<(), Error> {
+        super::volume_down().await
+    }
+
+    async fn set_subtitle(&self, id: i64) -> Result<(), Error> {
+        super::set_subtitle(id).await
+    }
+
+    async fn disable_subtitles(&self) -> Result<(), Error> {
+        super::disable_subtitles().await
+    }
+
+    async fn set_audio_track(&self, id: i64) -> Result<(), Error> {
+        super::set_audio_track(id).await
+    }
+
+    async fn set_video_track(&self, id: i64) -> Result<(), Error> {
+        super::set_video_track(id).await
+    }
+
+    async fn status(&self) -> Result<Status, Error> {
+        super::get_status().await
+    }
+
+    async fn playlist(&self) -> Result<Vec<PlaylistEntry>, Error> {
+        super::get_playlist().await
+    }
+
+    async fn playlist_next(&self) -> Result<(), Error> {
+        super::playlist_next().await
+    }
+
+    async fn playlist_previous(&self) -> Result<(), Error> {
+        super::playlist_previous().await
+    }
+}
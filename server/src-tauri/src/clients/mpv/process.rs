@@ -0,0 +1,215 @@
+use std::process::Stdio;
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde_json::json;
+use tokio::process::{Child, Command};
+use tokio::time::Instant;
+
+use super::{instances, pipe, Error};
+use crate::settings::{load_settings, MpvSettings};
+
+// Restarts back off from one second, doubling up to half a minute. After this
+// many crashes in a row mpv is left alone, a bad `binary` or `args` setting
+// would otherwise respawn it forever.
+const FIRST_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_RESTARTS: u32 = 5;
+// Running this long counts as a successful start and resets the backoff.
+const STABLE: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    // IPC path of the mpv we launched, for as long as it's running.
+    static ref MANAGED: Mutex<Option<String>> = Mutex::new(None);
+}
+
+/// Where to reach the mpv the server launched, if there is one.
+pub fn ipc_path() -> Option<String> {
+    MANAGED.lock().clone()
+}
+
+/// Launches mpv unless one is reachable already. It's restarted if it crashes,
+/// quitting it normally ends it for good.
+pub async fn start() -> Result<(), Error> {
+    if pipe::get_connection().await.is_ok() {
+        return Ok(());
+    }
+    if instances::current() != instances::DEFAULT {
+        return Err(Error::BadArgument(
+            "Only the default instance can be launched".to_string(),
+        ));
+    }
+
+    let settings = load_settings()
+        .mpv
+        .ok_or_else(|| Error::BadArgument("No mpv in settings".to_string()))?;
+    let ipc = generate_ipc_path();
+
+    let mut managed = MANAGED.lock();
+    if managed.is_some() {
+        // Launched already, still starting up.
+        return Ok(());
+    }
+    let child = spawn(&settings, &ipc)?;
+    *managed = Some(ipc.clone());
+    drop(managed);
+
+    tokio::spawn(supervise(child, settings, ipc));
+    Ok(())
+}
+
+/// Asks mpv to quit, whether the server launched it or not.
+pub async fn quit() -> Result<(), Error> {
+    pipe::command(json!(["quit"])).await?;
+    Ok(())
+}
+
+async fn supervise(mut child: Child, settings: MpvSettings, ipc: String) {
+    let mut restarts = 0;
+    let mut backoff = FIRST_BACKOFF;
+    let mut started = Instant::now();
+
+    loop {
+        match child.wait().await {
+            Ok(status) if status.success() => {
+                tracing::debug!("mpv quit");
+                break;
+            }
+            Ok(status) => {
+                if started.elapsed() >= STABLE {
+                    restarts = 0;
+                    backoff = FIRST_BACKOFF;
+                }
+                if restarts == MAX_RESTARTS {
+                    tracing::debug!(
+                        "mpv crashed ({}) {} times in a row, giving up",
+                        status,
+                        restarts + 1
+                    );
+                    break;
+                }
+                tracing::debug!("mpv crashed ({}), restarting in {:?}", status, backoff);
+                tokio::time::sleep(backoff).await;
+                restarts += 1;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                started = Instant::now();
+
+                match spawn(&settings, &ipc) {
+                    Ok(restarted) => child = restarted,
+                    Err(e) => {
+                        tracing::debug!("Error restarting mpv: {}", e);
+                        break;
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::debug!("Error waiting for mpv: {:?}", e);
+                break;
+            }
+        }
+    }
+
+    *MANAGED.lock() = None;
+}
+
+fn spawn(settings: &MpvSettings, ipc: &str) -> Result<Child, Error> {
+    let binary = settings.binary.as_deref().unwrap_or("mpv");
+    tracing::debug!("Starting {} with IPC at {}", binary, ipc);
+
+    Command::new(binary)
+        // Stay open with a window when nothing is loaded so the remote has
+        // something to control.
+        .arg("--idle=yes")
+        .arg("--force-window=yes")
+        .arg(format!("--input-ipc-server={}", ipc))
+        .args(settings.args.iter().flatten())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| Error::BadArgument(format!("Can't start {}: {}", binary, e)))
+}
+
+#[cfg(windows)]
+fn generate_ipc_path() -> String {
+    format!(r"\\.\pipe\mpvsocket-remote-{}", std::process::id())
+}
+
+#[cfg(unix)]
+fn generate_ipc_path() -> String {
+    std::env::temp_dir()
+        .join(format!("mpvsocket-remote-{}", std::process::id()))
+        .to_string_lossy()
+        .to_string()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+
+    /// Shell script standing in for mpv, counting its runs in `<script>.runs`.
+    fn fake_mpv(name: &str, exit_code: i32) -> PathBuf {
+        let script = std::env::temp_dir().join(format!("fake-mpv-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_file(script.with_extension("runs"));
+        std::fs::write(
+            &script,
+            format!("#!/bin/sh\necho run >> \"$0.runs\"\nexit {}\n", exit_code),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        script
+    }
+
+    fn runs(script: &Path) -> usize {
+        std::fs::read_to_string(script.with_extension("runs"))
+            .unwrap_or_default()
+            .lines()
+            .count()
+    }
+
+    fn fake_settings(script: &Path) -> MpvSettings {
+        MpvSettings {
+            pipe: String::new(),
+            subtitle_folders: None,
+            launch: Some(true),
+            binary: Some(script.to_string_lossy().to_string()),
+            args: None,
+            instances: None,
+            default_instance: None,
+            discover: None,
+        }
+    }
+
+    // Both cases in one test, so no other test forks while a script is still
+    // open for writing.
+    #[tokio::test(start_paused = true)]
+    async fn restarts_with_backoff_until_the_cap() {
+        let crashing = fake_mpv("crashing", 1);
+        let settings = fake_settings(&crashing);
+        let child = spawn(&settings, "unused").unwrap();
+
+        let started = Instant::now();
+        supervise(child, settings, "unused".to_string()).await;
+
+        assert_eq!(runs(&crashing), 1 + MAX_RESTARTS as usize);
+        // 1 + 2 + 4 + 8 + 16 seconds between the runs.
+        assert_eq!(started.elapsed().as_secs(), 31);
+        assert!(ipc_path().is_none());
+
+        let quitting = fake_mpv("quitting", 0);
+        let settings = fake_settings(&quitting);
+        let child = spawn(&settings, "unused").unwrap();
+        supervise(child, settings, "unused".to_string()).await;
+        assert_eq!(runs(&quitting), 1);
+
+        for script in [crashing, quitting] {
+            let _ = std::fs::remove_file(script.with_extension("runs"));
+            let _ = std::fs::remove_file(script);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::{pipe, Error};
+use crate::settings;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Png,
+    Jpg,
+}
+
+impl ImageFormat {
+    /// Format of a screenshot going by its extension.
+    fn of(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("png") => ImageFormat::Png,
+            _ => ImageFormat::Jpg,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpg => "jpg",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpg => "image/jpeg",
+        }
+    }
+
+    fn codec(self) -> image::ImageFormat {
+        match self {
+            ImageFormat::Png => image::ImageFormat::Png,
+            ImageFormat::Jpg => image::ImageFormat::Jpeg,
+        }
+    }
+}
+
+/// A screenshot in the gallery.
+#[derive(Serialize, Debug, Clone)]
+pub struct Screenshot {
+    pub name: String,
+    pub size: u64,
+    /// Seconds since the Unix epoch.
+    pub modified: u64,
+}
+
+fn screenshot_dir() -> PathBuf {
+    settings::get_cache_dir("screenshots")
+}
+
+/// Has mpv write a screenshot into the screenshot directory and returns its path.
+pub async fn take_screenshot(subtitles: bool, format: ImageFormat) -> Result<PathBuf, Error> {
+    let dir = screenshot_dir();
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| Error::BadArgument(format!("Can't create {}: {}", dir.display(), e)))?;
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_millis())
+        .unwrap_or_default();
+    let path = dir.join(format!("screenshot-{}.{}", millis, format.extension()));
+
+    // mpv picks the format from the extension.
+    let flags = if subtitles { "subtitles" } else { "video" };
+    pipe::command(json!(["screenshot-to-file", path.to_string_lossy(), flags])).await?;
+    Ok(path)
+}
+
+/// Gallery of saved screenshots, newest first.
+pub fn list_screenshots() -> Vec<Screenshot> {
+    let Ok(entries) = std::fs::read_dir(screenshot_dir()) else {
+        return vec![];
+    };
+
+    let mut screenshots: Vec<Screenshot> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some(Screenshot {
+                name: entry.file_name().to_str()?.to_string(),
+                size: metadata.len(),
+                modified: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                    .map(|modified| modified.as_secs())
+                    .unwrap_or(0),
+            })
+        })
+        .collect();
+    screenshots.sort_by(|a, b| b.modified.cmp(&a.modified).then(b.name.cmp(&a.name)));
+    screenshots
+}
+
+/// Path of a screenshot in the gallery, refusing names that point elsewhere.
+pub fn screenshot_path(name: &str) -> Result<PathBuf, Error> {
+    let path = screenshot_dir().join(name);
+    if Path::new(name).file_name() != Some(name.as_ref()) || !path.is_file() {
+        return Err(Error::BadArgument(format!("Unknown screenshot: {}", name)));
+    }
+    Ok(path)
+}
+
+pub fn delete_screenshot(name: &str) -> Result<(), Error> {
+    let path = screenshot_path(name)?;
+    std::fs::remove_file(&path)
+        .map_err(|e| Error::BadArgument(format!("Can't delete {}: {}", name, e)))
+}
+
+/// Reads a screenshot for sending, scaled down to `max_width` if it's wider,
+/// and returns the bytes with their content type. Unless `keep` is set the
+/// file is deleted afterwards.
+pub async fn read_screenshot(
+    path: PathBuf,
+    max_width: Option<u32>,
+    keep: bool,
+) -> Result<(Vec<u8>, &'static str), Error> {
+    let task = tokio::task::spawn_blocking(move || {
+        let image = read(&path, max_width);
+        if !keep {
+            let _ = std::fs::remove_file(&path);
+        }
+        image
+    });
+
+    task.await.map_err(|e| Error::Protocol(e.to_string()))?
+}
+
+fn read(path: &Path, max_width: Option<u32>) -> Result<(Vec<u8>, &'static str), Error> {
+    let format = ImageFormat::of(path);
+    let bytes = std::fs::read(path)
+        .map_err(|e| Error::BadArgument(format!("Can't read {}: {}", path.display(), e)))?;
+
+    let Some(max_width) = max_width.filter(|width| *width > 0) else {
+        return Ok((bytes, format.content_type()));
+    };
+    let image = image::load_from_memory_with_format(&bytes, format.codec())
+        .map_err(|e| Error::Protocol(e.to_string()))?;
+    if image.width() <= max_width {
+        return Ok((bytes, format.content_type()));
+    }
+
+    // Scaled down in the format it was taken in, a JPEG preview stays small.
+    let image = image.resize(max_width, u32::MAX, FilterType::Triangle);
+    let mut scaled = Cursor::new(vec![]);
+    image
+        .write_to(&mut scaled, format.codec())
+        .map_err(|e| Error::Protocol(e.to_string()))?;
+    Ok((scaled.into_inner(), format.content_type()))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, GenericImageView};
+
+    use super::*;
+
+    fn write_image(name: &str, format: ImageFormat) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "screenshot-test-{}-{}.{}",
+            std::process::id(),
+            name,
+            format.extension()
+        ));
+        DynamicImage::new_rgb8(400, 200)
+            .save_with_format(&path, format.codec())
+            .unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn scales_down_in_the_same_format() {
+        for format in [ImageFormat::Jpg, ImageFormat::Png] {
+            let path = write_image("scaled", format);
+
+            let (bytes, content_type) = read_screenshot(path.clone(), Some(100), false)
+                .await
+                .unwrap();
+            assert_eq!(content_type, format.content_type());
+            let image = image::load_from_memory_with_format(&bytes, format.codec()).unwrap();
+            assert_eq!(image.dimensions(), (100, 50));
+            assert!(!path.exists());
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_narrow_images_as_they_are() {
+        let path = write_image("narrow", ImageFormat::Jpg);
+        let original = std::fs::read(&path).unwrap();
+
+        let (bytes, content_type) = read_screenshot(path.clone(), Some(800), true)
+            .await
+            .unwrap();
+        assert_eq!(content_type, "image/jpeg");
+        assert_eq!(bytes, original);
+        assert!(path.exists());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{mpv, vlc, Error};
+use crate::settings::{load_settings, Backend, PlayerSettings};
+
+/// Common controls every backend the remote can drive has to provide.
+#[async_trait]
+pub trait MediaPlayer: Send + Sync {
+    async fn toggle_pause(&self) -> Result<(), Error>;
+
+    /// Seeks relative to the current position, in seconds.
+    async fn seek(&self, offset: f64) -> Result<(), Error>;
+
+    /// Seeks to an absolute position, in seconds.
+    async fn seek_to(&self, position: f64) -> Result<(), Error>;
+
+    async fn set_volume(&self, volume: i16) -> Result<(), Error>;
+    async fn volume_up(&self) -> Result<(), Error>;
+    async fn volume_down(&self) -> Result<(), Error>;
+
+    async fn set_subtitle(&self, id: i64) -> Result<(), Error>;
+
+    async fn disable_subtitles(&self) -> Result<(), Error> {
+        Err(Error::BadArgument(
+            "This player doesn't support subtitles".to_string(),
+        ))
+    }
+
+    async fn set_audio_track(&self, _id: i64) -> Result<(), Error> {
+        Err(Error::BadArgument(
+            "This player doesn't support selecting audio tracks".to_string(),
+        ))
+    }
+
+    async fn set_video_track(&self, _id: i64) -> Result<(), Error> {
+        Err(Error::BadArgument(
+            "This player doesn't support selecting video tracks".to_string(),
+        ))
+    }
+
+    async fn status(&self) -> Result<Status, Error>;
+
+    async fn playlist(&self) -> Result<Vec<PlaylistEntry>, Error>;
+    async fn playlist_next(&self) -> Result<(), Error>;
+    async fn playlist_previous(&self) -> Result<(), Error>;
+
+    /// Blocks until the player reports a change and returns what changed.
+    async fn wait_for_change(&self) -> Result<Vec<String>, Error> {
+        Err(Error::BadArgument(
+            "This player doesn't support change notifications".to_string(),
+        ))
+    }
+}
+
+/// Players configured in settings, falling back to the local mpv.
+pub fn configured_players() -> Vec<PlayerSettings> {
+    load_settings().players.unwrap_or_else(|| {
+        vec![PlayerSettings {
+            id: "mpv".to_string(),
+            backend: Backend::Mpv,
+        }]
+    })
+}
+
+pub fn get_player(id: &str) -> Result<Arc<dyn MediaPlayer>, Error> {
+    let player = configured_players()
+        .into_iter()
+        .find(|player| player.id == id)
+        .ok_or_else(|| Error::BadArgument(format!("Unknown player: {}", id)))?;
+
+    match player.backend {
+        Backend::Mpv => Ok(Arc::new(mpv::Mpv)),
+        Backend::Vlc => {
+            let settings = load_settings()
+                .vlc
+                .ok_or_else(|| Error::BadArgument("No vlc in settings".to_string()))?;
+            Ok(Arc::new(vlc::Vlc::new(settings)))
+        }
+        Backend::Mpd => {
+            let settings = load_settings()
+                .mpd
+                .ok_or_else(|| Error::BadArgument("No mpd in settings".to_string()))?;
+            Ok(Arc::new(super::mpd::Mpd::new(settings)))
+        }
+        Backend::Kodi => {
+            let settings = load_settings()
+                .kodi
+                .ok_or_else(|| Error::BadArgument("No kodi in settings".to_string()))?;
+            Ok(Arc::new(super::kodi::Kodi::new(settings)))
+        }
+        #[cfg(target_os = "linux")]
+        Backend::Mpris => {
+            let settings = load_settings()
+                .mpris
+                .unwrap_or(crate::settings::MprisSettings { player: None });
+            Ok(Arc::new(super::mpris::Mpris::new(settings)))
+        }
+        #[cfg(not(target_os = "linux"))]
+        Backend::Mpris => Err(Error::BadArgument(
+            "MPRIS is only available on Linux".to_string(),
+        )),
+    }
+}
+
+/// A subtitle track, or for mpv also a file found next to the playing one.
+/// Files that aren't `loaded` yet have negative ids, selecting one loads it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Subtitle {
+    pub id: i64,
+    pub title: String,
+    pub loaded: bool,
+}
+
+/// An audio or video track. `channels` is only known for audio.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Track {
+    pub id: i64,
+    pub lang: Option<String>,
+    pub title: Option<String>,
+    pub codec: Option<String>,
+    pub channels: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Chapter {
+    pub title: Option<String>,
+    /// Start of the chapter in seconds.
+    pub time: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlaylistEntry {
+    pub filename: String,
+    pub title: Option<String>,
+    #[serde(default)]
+    pub current: bool,
+    #[serde(default)]
+    pub playing: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Status {
+    pub running: bool,
+    pub paused: bool,
+    pub volume: f64,
+    pub muted: bool,
+    // Whether the normalization filter is in the audio filter chain.
+    pub normalize: bool,
+    pub position: f64,
+    pub length: f64,
+    pub title: String,
+    pub file: String,
+    pub subtitle: Option<i64>,
+    pub subtitles: Vec<Subtitle>,
+    pub secondary_subtitle: Option<i64>,
+    pub subtitle_delay: f64,
+    pub subtitle_scale: f64,
+    pub subtitle_position: f64,
+    pub subtitle_visible: bool,
+    pub audio: Option<i64>,
+    pub audio_tracks: Vec<Track>,
+    pub video: Option<i64>,
+    pub video_tracks: Vec<Track>,
+    // Index into `chapters`, -1 before the first one starts.
+    pub chapter: Option<i64>,
+    pub chapters: Vec<Chapter>,
+    pub playlist: Vec<PlaylistEntry>,
+    // Index into `playlist`, `None` when nothing from it is playing.
+    pub playlist_position: Option<i64>,
+    pub loop_file: bool,
+    pub loop_playlist: bool,
+    pub speed: f64,
+    pub pitch_correction: bool,
+    // Loop points in seconds, `None` while unset.
+    pub ab_loop_a: Option<f64>,
+    pub ab_loop_b: Option<f64>,
+}
+
+// What a player with nothing loaded looks like, backends fill in what they support.
+impl Default for Status {
+    fn default() -> Self {
+        Status {
+            running: true,
+            paused: true,
+            volume: 0.0,
+            muted: false,
+            normalize: false,
+            position: 0.0,
+            length: 0.0,
+            title: String::new(),
+            file: String::new(),
+            subtitle: None,
+            subtitles: vec![],
+            secondary_subtitle: None,
+            subtitle_delay: 0.0,
+            subtitle_scale: 1.0,
+            subtitle_position: 100.0,
+            subtitle_visible: true,
+            audio: None,
+            audio_tracks: vec![],
+            video: None,
+            video_tracks: vec![],
+            chapter: None,
+            chapters: vec![],
+            playlist: vec![],
+            playlist_position: None,
+            loop_file: false,
+            loop_playlist: false,
+            speed: 1.0,
+            pitch_correction: true,
+            ab_loop_a: None,
+            ab_loop_b: None,
+        }
+    }
+}
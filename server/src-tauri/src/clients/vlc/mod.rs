@@ -0,0 +1,405 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::clients::http::{map_error, CLIENT};
+use crate::clients::MediaPlayer;
+use crate::clients::{Error, PlaylistEntry, Status, Subtitle, Track};
+use crate::settings::VlcSettings;
+
+// VLC reports volume on a 0-512 scale where 256 is 100%.
+const VOLUME_SCALE: f64 = 256.0 / 100.0;
+const VOLUME_STEP: f64 = 2.0;
+
+/// VLC driven through its built-in HTTP interface (`--extraintf http`).
+pub struct Vlc {
+    settings: VlcSettings,
+}
+
+impl Vlc {
+    pub fn new(settings: VlcSettings) -> Self {
+        Vlc { settings }
+    }
+
+    async fn request(&self, path: &str, query: &[(&str, String)]) -> Result<Value, Error> {
+        let url = format!(
+            "http://{}:{}/requests/{}",
+            self.settings.host, self.settings.port, path
+        );
+
+        let response = CLIENT
+            .get(url)
+            // VLC only uses the password, the user name has to be empty.
+            .basic_auth("", Some(&self.settings.password))
+            .query(query)
+            .send()
+            .await
+            .map_err(map_error)?;
+
+        if !response.status().is_success() {
+            return Err(Error::Protocol(format!(
+                "VLC answered {}",
+                response.status()
+            )));
+        }
+
+        response.json::<Value>().await.map_err(map_error)
+    }
+
+    async fn command(&self, command: &str, val: Option<String>) -> Result<Value, Error> {
+        let mut query = vec![("command", command.to_string())];
+        if let Some(val) = val {
+            query.push(("val", val));
+        }
+        self.request("status.json", &query).await
+    }
+}
+
+fn status(json: &Value) -> Status {
+    let meta = &json["information"]["category"]["meta"];
+    let title = match &meta["title"] {
+        Value::Null => &meta["filename"],
+        title => title,
+    };
+
+    Status {
+        paused: json["state"] != "playing",
+        volume: json["volume"].as_f64().unwrap_or(0.0) / VOLUME_SCALE,
+        position: json["time"].as_f64().unwrap_or(0.0),
+        length: json["length"].as_f64().unwrap_or(0.0),
+        title: title.to_string(),
+        file: meta["filename"].to_string(),
+        // status.json doesn't say which subtitle track is selected.
+        subtitle: None,
+        subtitles: subtitles(json),
+        // Like subtitles, the selected tracks aren't reported.
+        audio_tracks: tracks(json, "Audio"),
+        video_tracks: tracks(json, "Video"),
+        ..Default::default()
+    }
+}
+
+/// `Stream N` entries of the given type, VLC's track ids are their N.
+fn streams<'a>(json: &'a Value, kind: &str) -> Vec<(i64, &'a Value)> {
+    let mut streams = vec![];
+    let Some(category) = json["information"]["category"].as_object() else {
+        return streams;
+    };
+
+    for (name, stream) in category {
+        if stream["Type"] != kind {
+            continue;
+        }
+        if let Some(id) = name
+            .strip_prefix("Stream ")
+            .and_then(|id| id.parse::<i64>().ok())
+        {
+            streams.push((id, stream));
+        }
+    }
+
+    streams.sort_by_key(|(id, _)| *id);
+    streams
+}
+
+fn subtitles(json: &Value) -> Vec<Subtitle> {
+    streams(json, "Subtitle")
+        .into_iter()
+        .map(|(id, stream)| {
+            let lang = stream["Language"].as_str().unwrap_or("null");
+            let title = match stream["Description"].as_str() {
+                Some(description) => format!("{} - {}", lang, description),
+                None => lang.to_string(),
+            };
+            Subtitle {
+                id,
+                title,
+                loaded: true,
+            }
+        })
+        .collect()
+}
+
+fn tracks(json: &Value, kind: &str) -> Vec<Track> {
+    streams(json, kind)
+        .into_iter()
+        .map(|(id, stream)| {
+            let text = |key: &str| stream[key].as_str().map(str::to_string);
+            Track {
+                id,
+                lang: text("Language"),
+                title: text("Description"),
+                codec: text("Codec"),
+                // Only given as a layout name like "Stereo" or "3F2R/LFE".
+                channels: None,
+            }
+        })
+        .collect()
+}
+
+/// `playing` is whether VLC is playing rather than paused or stopped,
+/// playlist.json only marks which item is current.
+fn playlist(node: &Value, playing: bool, entries: &mut Vec<PlaylistEntry>) {
+    if let Some(uri) = node["uri"].as_str() {
+        let current = node["current"] == "current";
+        entries.push(PlaylistEntry {
+            filename: uri.to_string(),
+            title: node["name"].as_str().map(str::to_string),
+            current,
+            playing: current && playing,
+        });
+    }
+
+    for child in node["children"].as_array().into_iter().flatten() {
+        playlist(child, playing, entries);
+    }
+}
+
+#[async_trait]
+impl MediaPlayer for Vlc {
+    async fn toggle_pause(&self) -> Result<(), Error> {
+        self.command("pl_pause", None).await?;
+        Ok(())
+    }
+
+    async fn seek(&self, offset: f64) -> Result<(), Error> {
+        let offset = offset.round() as i64;
+        self.command("seek", Some(format!("{:+}", offset))).await?;
+        Ok(())
+    }
+
+    async fn seek_to(&self, position: f64) -> Result<(), Error> {
+        let position = position.round() as i64;
+        self.command("seek", Some(position.to_string())).await?;
+        Ok(())
+    }
+
+    async fn set_volume(&self, volume: i16) -> Result<(), Error> {
+        if volume < 0 {
+            return Err(Error::BadArgument(format!(
+                "Volume must not be negative, got {}",
+                volume
+            )));
+        }
+        let volume = (f64::from(volume) * VOLUME_SCALE).round();
+        self.command("volume", Some(volume.to_string())).await?;
+        Ok(())
+    }
+
+    async fn volume_up(&self) -> Result<(), Error> {
+        let volume = self.status().await?.volume + VOLUME_STEP;
+        self.set_volume(volume.round() as i16).await
+    }
+
+    async fn volume_down(&self) -> Result<(), Error> {
+        let volume = (self.status().await?.volume - VOLUME_STEP).max(0.0);
+        self.set_volume(volume.round() as i16).await
+    }
+
+    async fn set_subtitle(&self, id: i64) -> Result<(), Error> {
+        self.command("subtitle_track", Some(id.to_string())).await?;
+        Ok(())
+    }
+
+    async fn disable_subtitles(&self) -> Result<(), Error> {
+        self.command("subtitle_track", Some("-1".to_string()))
+            .await?;
+        Ok(())
+    }
+
+    async fn set_audio_track(&self, id: i64) -> Result<(), Error> {
+        self.command("audio_track", Some(id.to_string())).await?;
+        Ok(())
+    }
+
+    async fn set_video_track(&self, id: i64) -> Result<(), Error> {
+        self.command("video_track", Some(id.to_string())).await?;
+        Ok(())
+    }
+
+    async fn status(&self) -> Result<Status, Error> {
+        let json = self.request("status.json", &[]).await?;
+        Ok(status(&json))
+    }
+
+    async fn playlist(&self) -> Result<Vec<PlaylistEntry>, Error> {
+        let status = self.request("status.json", &[]).await?;
+        let json = self.request("playlist.json", &[]).await?;
+        let mut entries = vec![];
+        playlist(&json, status["state"] == "playing", &mut entries);
+        Ok(entries)
+    }
+
+    async fn playlist_next(&self) -> Result<(), Error> {
+        self.command("pl_next", None).await?;
+        Ok(())
+    }
+
+    async fn playlist_previous(&self) -> Result<(), Error> {
+        self.command("pl_previous", None).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::clients::http::stub;
+
+    fn vlc(port: u16) -> Vlc {
+        Vlc::new(VlcSettings {
+            host: "127.0.0.1".to_string(),
+            port,
+            password: "secret".to_string(),
+        })
+    }
+
+    fn status_json() -> Value {
+        json!({
+            "state": "playing",
+            "volume": 128,
+            "time": 42,
+            "length": 1300,
+            "information": {
+                "category": {
+                    "meta": { "filename": "episode.mkv" },
+                    "Stream 0": { "Type": "Video", "Codec": "H264 - MPEG-4 AVC (part 10) (avc1)" },
+                    "Stream 1": { "Type": "Audio", "Language": "English", "Codec": "aac" },
+                    "Stream 3": { "Type": "Subtitle", "Language": "German", "Description": "Forced" },
+                    "Stream 2": { "Type": "Subtitle", "Language": "English" },
+                },
+            },
+        })
+    }
+
+    #[test]
+    fn parses_status() {
+        let status = status(&status_json());
+
+        assert!(!status.paused);
+        assert_eq!(status.volume, 50.0);
+        assert_eq!(status.position, 42.0);
+        assert_eq!(status.length, 1300.0);
+        // No title in the metadata, the file name stands in.
+        assert_eq!(status.title, "\"episode.mkv\"");
+        assert_eq!(status.file, "\"episode.mkv\"");
+
+        let subtitles: Vec<(i64, &str)> = status
+            .subtitles
+            .iter()
+            .map(|subtitle| (subtitle.id, subtitle.title.as_str()))
+            .collect();
+        assert_eq!(subtitles, [(2, "English"), (3, "German - Forced")]);
+        assert_eq!(status.audio_tracks.len(), 1);
+        assert_eq!(status.audio_tracks[0].lang.as_deref(), Some("English"));
+        assert_eq!(status.video_tracks[0].id, 0);
+    }
+
+    #[test]
+    fn parses_nested_playlist() {
+        let json = json!({
+            "name": "",
+            "children": [
+                {
+                    "name": "Playlist",
+                    "children": [
+                        { "name": "One", "uri": "file:///one.mkv" },
+                        { "name": "Two", "uri": "file:///two.mkv", "current": "current" },
+                    ],
+                },
+                { "name": "Media Library", "children": [] },
+            ],
+        });
+
+        for playing in [true, false] {
+            let mut entries = vec![];
+            playlist(&json, playing, &mut entries);
+
+            let entries: Vec<(&str, bool, bool)> = entries
+                .iter()
+                .map(|entry| (entry.filename.as_str(), entry.current, entry.playing))
+                .collect();
+            assert_eq!(
+                entries,
+                [
+                    ("file:///one.mkv", false, false),
+                    ("file:///two.mkv", true, playing)
+                ]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn fetches_status_over_http() {
+        let (port, requests) = stub::serve(vec![status_json()]).await;
+
+        let status = vlc(port).status().await.unwrap();
+        assert_eq!(status.length, 1300.0);
+
+        let requests = requests.await.unwrap();
+        assert_eq!(requests[0].line, "GET /requests/status.json");
+    }
+
+    #[tokio::test]
+    async fn seeks_with_explicit_sign() {
+        let (port, requests) = stub::serve(vec![json!({}), json!({}), json!({})]).await;
+        let vlc = vlc(port);
+
+        vlc.seek(10.4).await.unwrap();
+        vlc.seek(-5.0).await.unwrap();
+        vlc.seek_to(90.0).await.unwrap();
+
+        // VLC treats an unsigned value as absolute, so forward skips need the `+`.
+        let lines: Vec<String> = requests
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|request| request.line)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "GET /requests/status.json?command=seek&val=%2B10",
+                "GET /requests/status.json?command=seek&val=-5",
+                "GET /requests/status.json?command=seek&val=90",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn paused_playlist_is_not_playing() {
+        let (port, _) = stub::serve(vec![
+            json!({ "state": "paused" }),
+            json!({ "children": [{ "name": "One", "uri": "file:///one.mkv", "current": "current" }] }),
+        ])
+        .await;
+
+        let entries = vlc(port).playlist().await.unwrap();
+        assert!(entries[0].current);
+        assert!(!entries[0].playing);
+    }
+
+    #[tokio::test]
+    async fn rejects_negative_volume() {
+        // Nothing is served, the volume never reaches VLC.
+        let (port, _) = stub::serve(vec![]).await;
+
+        let result = vlc(port).set_volume(-1).await;
+        assert!(matches!(result, Err(Error::BadArgument(_))));
+    }
+
+    #[tokio::test]
+    async fn unreachable_is_not_running() {
+        // Bound and dropped, so nothing listens there.
+        let port = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let result = vlc(port).status().await;
+        assert!(matches!(result, Err(Error::NotRunning)));
+    }
+}
@@ -1,5 +1,6 @@
 mod api;
 mod clients;
+mod library;
 mod settings;
 
 use lazy_static::lazy_static;
@@ -25,6 +26,8 @@ pub fn run() {
         api::init().await;
         tracing::debug!("Server stopped");
     });
+    RUNTIME.spawn(clients::mpv::observe());
+    RUNTIME.spawn(library::index());
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use lazy_static::lazy_static;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use super::{roots, Kind};
+use crate::settings;
+
+// Changes are picked up by watching the roots, this rescan catches whatever the
+// watcher can't see, like network shares, and roots changed in settings.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(30 * 60);
+// Quiet time after a change before rescanning, so a copy or a batch of
+// downloads is picked up in one go.
+const SETTLE: Duration = Duration::from_secs(2);
+
+lazy_static! {
+    static ref INDEX: RwLock<Vec<IndexEntry>> = RwLock::new(vec![]);
+    static ref EPISODE: Regex =
+        Regex::new(r"(?i)\bs(\d{1,2})[ ]?e(\d{1,3})\b|\b(\d{1,2})x(\d{2,3})\b").unwrap();
+    static ref YEAR: Regex = Regex::new(r"\b(19\d{2}|20\d{2})\b").unwrap();
+}
+
+/// A playable file under one of the media roots.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexEntry {
+    pub path: String,
+    pub name: String,
+    pub kind: Kind,
+    pub size: u64,
+    /// Seconds since the Unix epoch.
+    pub modified: u64,
+    /// Show or movie name guessed from the file name.
+    pub title: String,
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+    pub year: Option<u32>,
+}
+
+/// Everything indexed so far.
+pub fn entries() -> Vec<IndexEntry> {
+    INDEX.read().clone()
+}
+
+/// Keeps the index in sync with the media roots, rescanning when something
+/// under them changes. The last index is loaded from disk first so searches
+/// work before the first walk finishes.
+pub async fn index() {
+    if let Some(saved) = load() {
+        tracing::debug!("Loaded {} library entries", saved.len());
+        *INDEX.write() = saved;
+    }
+
+    let (sender, mut changes) = mpsc::unbounded_channel();
+    let mut watched = vec![];
+    // Dropping the watcher stops it.
+    let mut _watcher = None;
+
+    loop {
+        let roots = roots();
+        if roots != watched {
+            _watcher = watch(&roots, sender.clone());
+            watched = roots;
+        }
+
+        rescan().await;
+
+        tokio::select! {
+            _ = changes.recv() => {
+                tokio::time::sleep(SETTLE).await;
+                while changes.try_recv().is_ok() {}
+            }
+            _ = tokio::time::sleep(RESCAN_INTERVAL) => {}
+        }
+    }
+}
+
+fn watch(roots: &[PathBuf], changes: mpsc::UnboundedSender<()>) -> Option<RecommendedWatcher> {
+    let watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) => {
+                if matches!(
+                    event.kind,
+                    EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                ) {
+                    let _ = changes.send(());
+                }
+            }
+            Err(e) => tracing::debug!("Error watching library: {:?}", e),
+        });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            tracing::debug!("Can't watch library, rescanning periodically: {:?}", e);
+            return None;
+        }
+    };
+
+    for root in roots {
+        if let Err(e) = watcher.watch(root, RecursiveMode::Recursive) {
+            tracing::debug!("Can't watch {}: {:?}", root.display(), e);
+        }
+    }
+    Some(watcher)
+}
+
+async fn rescan() {
+    let previous: HashMap<String, IndexEntry> = INDEX
+        .read()
+        .iter()
+        .map(|entry| (entry.path.clone(), entry.clone()))
+        .collect();
+
+    match tokio::task::spawn_blocking(move || scan(&previous)).await {
+        Ok(entries) => {
+            tracing::debug!("Indexed {} library entries", entries.len());
+            save(&entries);
+            *INDEX.write() = entries;
+        }
+        Err(e) => tracing::debug!("Error indexing library: {:?}", e),
+    }
+}
+
+fn scan(previous: &HashMap<String, IndexEntry>) -> Vec<IndexEntry> {
+    let mut entries = vec![];
+    for root in roots() {
+        walk(&root, previous, &mut entries);
+    }
+    entries
+}
+
+fn walk(dir: &Path, previous: &HashMap<String, IndexEntry>, entries: &mut Vec<IndexEntry>) {
+    let Ok(children) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for child in children.filter_map(|child| child.ok()) {
+        if child.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        // Not following symlinks, directories could loop and either could leave the root.
+        let Ok(file_type) = child.file_type() else {
+            continue;
+        };
+        if file_type.is_symlink() {
+            continue;
+        }
+        let path = child.path();
+        if file_type.is_dir() {
+            walk(&path, previous, entries);
+            continue;
+        }
+
+        let kind = Kind::of(&path);
+        if kind != Kind::Video && kind != Kind::Audio {
+            continue;
+        }
+        let Ok(metadata) = child.metadata() else {
+            continue;
+        };
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|modified| modified.as_secs())
+            .unwrap_or(0);
+
+        let key = path.to_string_lossy().to_string();
+        // Unchanged files keep what was parsed last time.
+        if let Some(entry) = previous.get(&key) {
+            if entry.modified == modified && entry.size == metadata.len() {
+                entries.push(entry.clone());
+                continue;
+            }
+        }
+
+        entries.push(parse(path, kind, metadata.len(), modified));
+    }
+}
+
+/// Guesses title, season, episode and year from names like
+/// `Show.Name.S01E02.720p.mkv` or `Movie Name (2010).mp4`.
+fn parse(path: PathBuf, kind: Kind, size: u64, modified: u64) -> IndexEntry {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().replace(['.', '_'], " "))
+        .unwrap_or_default();
+
+    let mut title_end = stem.len();
+    let (mut season, mut episode) = (None, None);
+    if let Some(captures) = EPISODE.captures(&stem) {
+        let number = |a: usize, b: usize| {
+            captures
+                .get(a)
+                .or_else(|| captures.get(b))
+                .and_then(|number| number.as_str().parse().ok())
+        };
+        season = number(1, 3);
+        episode = number(2, 4);
+        title_end = captures.get(0).map(|m| m.start()).unwrap_or(title_end);
+    }
+
+    let mut year = None;
+    // A year at the very start is more likely part of the title, like `2001 A Space Odyssey`.
+    if let Some(found) = YEAR.find_iter(&stem).find(|found| found.start() > 0) {
+        year = found.as_str().parse().ok();
+        title_end = title_end.min(found.start());
+    }
+
+    let title = stem[..title_end]
+        .trim_end_matches(|c: char| c.is_whitespace() || "-([".contains(c))
+        .to_string();
+
+    IndexEntry {
+        path: path.to_string_lossy().to_string(),
+        title: if title.is_empty() { stem } else { title },
+        name,
+        kind,
+        size,
+        modified,
+        season,
+        episode,
+        year,
+    }
+}
+
+fn index_path() -> PathBuf {
+    settings::get_cache_dir("library").join("index.json")
+}
+
+fn load() -> Option<Vec<IndexEntry>> {
+    let buf = std::fs::read(index_path()).ok()?;
+    serde_json::from_slice(&buf).ok()
+}
+
+fn save(entries: &[IndexEntry]) {
+    let path = index_path();
+    let result = std::fs::create_dir_all(path.parent().unwrap()).and_then(|_| {
+        let json = serde_json::to_vec(entries)?;
+        std::fs::write(&path, json)
+    });
+
+    if let Err(e) = result {
+        tracing::debug!("Error saving library index: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names() {
+        // Name, then the expected title and [season, episode, year].
+        let cases: &[(&str, &str, [Option<u32>; 3])] = &[
+            (
+                "Show.Name.S01E02.720p.mkv",
+                "Show Name",
+                [Some(1), Some(2), None],
+            ),
+            (
+                "show_name_s1e10.mp4",
+                "show name",
+                [Some(1), Some(10), None],
+            ),
+            (
+                "Show Name S02 E003.mkv",
+                "Show Name",
+                [Some(2), Some(3), None],
+            ),
+            (
+                "Show Name - 3x07 - Title.avi",
+                "Show Name",
+                [Some(3), Some(7), None],
+            ),
+            (
+                "Movie Name (2010).mp4",
+                "Movie Name",
+                [None, None, Some(2010)],
+            ),
+            (
+                "Movie.Name.[1999].1080p.mkv",
+                "Movie Name",
+                [None, None, Some(1999)],
+            ),
+            (
+                "Show.2019.S01E01.mkv",
+                "Show",
+                [Some(1), Some(1), Some(2019)],
+            ),
+            (
+                "2001 A Space Odyssey.mkv",
+                "2001 A Space Odyssey",
+                [None; 3],
+            ),
+            ("1917.mkv", "1917", [None; 3]),
+            ("01.mkv", "01", [None; 3]),
+            ("Album - 1080 Track.flac", "Album - 1080 Track", [None; 3]),
+        ];
+
+        for (name, title, [season, episode, year]) in cases {
+            let entry = parse(PathBuf::from("/media").join(name), Kind::Video, 0, 0);
+            assert_eq!(
+                (
+                    entry.title.as_str(),
+                    entry.season,
+                    entry.episode,
+                    entry.year
+                ),
+                (*title, *season, *episode, *year),
+                "{}",
+                name
+            );
+            assert_eq!(entry.name, *name);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_symlinks() {
+        let dir = std::env::temp_dir().join(format!("index-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("root")).unwrap();
+        std::fs::create_dir_all(dir.join("outside")).unwrap();
+        std::fs::write(dir.join("root/movie.mkv"), "").unwrap();
+        std::fs::write(dir.join("outside/secret.mkv"), "").unwrap();
+        std::os::unix::fs::symlink(dir.join("outside/secret.mkv"), dir.join("root/file.mkv"))
+            .unwrap();
+        std::os::unix::fs::symlink(dir.join("outside"), dir.join("root/folder")).unwrap();
+
+        let mut entries = vec![];
+        walk(&dir.join("root"), &HashMap::new(), &mut entries);
+        let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["movie.mkv"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
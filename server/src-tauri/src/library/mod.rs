@@ -0,0 +1,274 @@
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::clients::Error;
+use crate::settings::load_settings;
+
+mod index;
+mod search;
+
+pub use index::index;
+pub use search::search;
+
+const VIDEO: [&str; 13] = [
+    "mkv", "mp4", "m4v", "avi", "mov", "wmv", "webm", "mpg", "mpeg", "ts", "m2ts", "flv", "ogv",
+];
+const AUDIO: [&str; 10] = [
+    "mp3", "flac", "ogg", "opus", "m4a", "aac", "wav", "wma", "alac", "ape",
+];
+const SUBTITLE: [&str; 4] = ["srt", "ass", "ssa", "vtt"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Directory,
+    Video,
+    Audio,
+    Subtitle,
+    Other,
+}
+
+impl Kind {
+    /// Guesses a file's kind from its extension.
+    pub fn of(path: &Path) -> Kind {
+        let Some(extension) = path.extension().and_then(|extension| extension.to_str()) else {
+            return Kind::Other;
+        };
+        let extension = extension.to_lowercase();
+        let extension = extension.as_str();
+
+        if VIDEO.contains(&extension) {
+            Kind::Video
+        } else if AUDIO.contains(&extension) {
+            Kind::Audio
+        } else if SUBTITLE.contains(&extension) {
+            Kind::Subtitle
+        } else {
+            Kind::Other
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    Name,
+    Size,
+    Modified,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Entry {
+    pub name: String,
+    pub path: String,
+    pub kind: Kind,
+    /// In bytes, `None` for directories.
+    pub size: Option<u64>,
+    /// Seconds since the Unix epoch.
+    pub modified: Option<u64>,
+}
+
+/// One page of a directory listing, `total` counts every matching entry.
+#[derive(Serialize, Debug, Clone)]
+pub struct Listing {
+    /// `None` for the list of roots.
+    pub path: Option<String>,
+    pub entries: Vec<Entry>,
+    pub total: usize,
+}
+
+pub struct Query {
+    /// Kinds of files to include, everything when empty. Directories are always listed.
+    pub kinds: Vec<Kind>,
+    pub sort: Sort,
+    pub descending: bool,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// Configured media roots that exist on this machine.
+pub fn roots() -> Vec<PathBuf> {
+    load_settings()
+        .media_roots
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|root| std::fs::canonicalize(root).ok())
+        .collect()
+}
+
+/// Resolves a path, refusing anything outside the configured media roots.
+pub fn resolve(path: &str) -> Result<PathBuf, Error> {
+    resolve_in(path, &roots())
+}
+
+fn resolve_in(path: &str, roots: &[PathBuf]) -> Result<PathBuf, Error> {
+    if roots.is_empty() {
+        return Err(Error::BadArgument("No media roots in settings".to_string()));
+    }
+
+    // Canonical paths so `..` and symlinks can't be used to get out of a root.
+    let path = std::fs::canonicalize(path)
+        .map_err(|e| Error::BadArgument(format!("Can't open {}: {}", path, e)))?;
+
+    if !roots.iter().any(|root| path.starts_with(root)) {
+        return Err(Error::BadArgument(format!(
+            "{} is not in a media root",
+            path.display()
+        )));
+    }
+    Ok(path)
+}
+
+/// Lists a directory under one of the roots, or the roots themselves.
+pub fn list(path: Option<&str>, query: &Query) -> Result<Listing, Error> {
+    let (path, mut entries): (_, Vec<Entry>) = match path {
+        Some(path) => {
+            let dir = resolve(path)?;
+            let entries = std::fs::read_dir(&dir)
+                .map_err(|e| Error::BadArgument(format!("Can't list {}: {}", dir.display(), e)))?
+                .filter_map(|entry| entry.ok())
+                // Hidden files are rarely something to play.
+                .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+                .filter_map(|entry| entry_for(&entry.path()))
+                .collect();
+            (Some(dir.to_string_lossy().to_string()), entries)
+        }
+        None => {
+            let entries = roots().iter().filter_map(|root| entry_for(root)).collect();
+            (None, entries)
+        }
+    };
+
+    entries.retain(|entry| {
+        entry.kind == Kind::Directory || query.kinds.is_empty() || query.kinds.contains(&entry.kind)
+    });
+
+    entries.sort_by(|a, b| {
+        let order = match query.sort {
+            Sort::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            Sort::Size => a.size.cmp(&b.size),
+            Sort::Modified => a.modified.cmp(&b.modified),
+        };
+        let order = if query.descending {
+            order.reverse()
+        } else {
+            order
+        };
+        // Directories stay on top whichever way it's sorted.
+        (b.kind == Kind::Directory)
+            .cmp(&(a.kind == Kind::Directory))
+            .then(order)
+    });
+
+    let total = entries.len();
+    let entries = entries
+        .into_iter()
+        .skip(query.offset)
+        .take(query.limit)
+        .collect();
+
+    Ok(Listing {
+        path,
+        entries,
+        total,
+    })
+}
+
+fn entry_for(path: &Path) -> Option<Entry> {
+    // Follows symlinks, a broken one is skipped.
+    let metadata = std::fs::metadata(path).ok()?;
+    let kind = if metadata.is_dir() {
+        Kind::Directory
+    } else {
+        Kind::of(path)
+    };
+
+    Some(Entry {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string_lossy().to_string()),
+        path: path.to_string_lossy().to_string(),
+        kind,
+        size: (!metadata.is_dir()).then_some(metadata.len()),
+        modified: metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|modified| modified.as_secs()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `root/movie.mkv`, `root2/secret.mkv` and `outside/secret.mkv` in a fresh directory.
+    fn tree(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("library-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (sub, file) in [
+            ("root", "movie.mkv"),
+            ("root2", "secret.mkv"),
+            ("outside", "secret.mkv"),
+        ] {
+            std::fs::create_dir_all(dir.join(sub)).unwrap();
+            std::fs::write(dir.join(sub).join(file), "").unwrap();
+        }
+        std::fs::canonicalize(dir).unwrap()
+    }
+
+    fn resolves(path: &Path, roots: &[PathBuf]) -> bool {
+        resolve_in(&path.to_string_lossy(), roots).is_ok()
+    }
+
+    #[test]
+    fn resolves_inside_roots() {
+        let dir = tree("inside");
+        let roots = [dir.join("root")];
+
+        let path = resolve_in(&dir.join("root/./movie.mkv").to_string_lossy(), &roots).unwrap();
+        assert_eq!(path, dir.join("root/movie.mkv"));
+        assert!(!resolves(&dir.join("root/missing.mkv"), &roots));
+        assert!(!resolves(&dir.join("root/movie.mkv"), &[]));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_parent_escapes() {
+        let dir = tree("parent");
+        let roots = [dir.join("root")];
+
+        assert!(!resolves(&dir.join("root/../outside/secret.mkv"), &roots));
+        assert!(!resolves(&dir.join("root/.."), &roots));
+        assert!(resolves(&dir.join("outside/../root/movie.mkv"), &roots));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_sibling_with_same_prefix() {
+        let dir = tree("sibling");
+        let roots = [dir.join("root")];
+
+        assert!(!resolves(&dir.join("root2/secret.mkv"), &roots));
+        assert!(!resolves(&dir.join("root2"), &roots));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_symlinks_out_of_roots() {
+        let dir = tree("symlink");
+        let roots = [dir.join("root")];
+        std::os::unix::fs::symlink(dir.join("outside/secret.mkv"), dir.join("root/file.mkv"))
+            .unwrap();
+        std::os::unix::fs::symlink(dir.join("outside"), dir.join("root/folder")).unwrap();
+
+        assert!(!resolves(&dir.join("root/file.mkv"), &roots));
+        assert!(!resolves(&dir.join("root/folder/secret.mkv"), &roots));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+use serde::Serialize;
+
+use super::index::{entries, IndexEntry};
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    pub entry: IndexEntry,
+    pub score: u32,
+}
+
+/// Fuzzy search over the index. Every word of the query has to show up in
+/// the file name or its folder, either as is or with gaps (`brkbd` finds
+/// `Breaking Bad`), exact matches rank higher.
+pub fn search(query: &str, limit: usize) -> Vec<SearchResult> {
+    let words: Vec<String> = query
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect();
+    if words.is_empty() {
+        return vec![];
+    }
+
+    let mut results: Vec<SearchResult> = entries()
+        .into_iter()
+        .filter_map(|entry| {
+            let score = score(&haystack(&entry), &words)?;
+            Some(SearchResult { entry, score })
+        })
+        .collect();
+
+    results.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.entry.title.cmp(&b.entry.title))
+            .then_with(|| a.entry.season.cmp(&b.entry.season))
+            .then_with(|| a.entry.episode.cmp(&b.entry.episode))
+    });
+    results.truncate(limit);
+    results
+}
+
+fn haystack(entry: &IndexEntry) -> String {
+    // The folder often has the show name when the file itself is just `01.mkv`.
+    let folder = std::path::Path::new(&entry.path)
+        .parent()
+        .and_then(|parent| parent.file_name())
+        .map(|folder| folder.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    format!("{} {} {}", entry.title, entry.name, folder)
+        .replace(['.', '_'], " ")
+        .to_lowercase()
+}
+
+fn score(haystack: &str, words: &[String]) -> Option<u32> {
+    let mut score = 0;
+    for word in words {
+        let length = word.chars().count() as u32;
+        if haystack
+            .split_whitespace()
+            .any(|part| part.starts_with(word.as_str()))
+        {
+            score += 3 * length;
+        } else if haystack.contains(word.as_str()) {
+            score += 2 * length;
+        } else if is_subsequence(word, haystack) {
+            score += length;
+        } else {
+            return None;
+        }
+    }
+    Some(score)
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.by_ref().any(|h| h == c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(query: &str) -> Vec<String> {
+        query.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn scores_matches() {
+        let haystack = "breaking bad breaking bad s01e01 mkv tv";
+        let cases: &[(&str, Option<u32>)] = &[
+            // Word prefix, 3 per character.
+            ("break", Some(15)),
+            // Inside a word, 2 per character.
+            ("king", Some(8)),
+            // With gaps, 1 per character.
+            ("brkbd", Some(5)),
+            ("break bad", Some(24)),
+            ("break xyz", None),
+            ("zq", None),
+        ];
+
+        for (query, expected) in cases {
+            assert_eq!(score(haystack, &words(query)), *expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn finds_subsequences() {
+        let cases = [
+            ("", "anything", true),
+            ("abc", "abc", true),
+            ("ac", "abc", true),
+            ("ca", "abc", false),
+            ("aa", "a", false),
+            ("abc", "", false),
+        ];
+
+        for (needle, haystack, expected) in cases {
+            assert_eq!(
+                is_subsequence(needle, haystack),
+                expected,
+                "{} in {}",
+                needle,
+                haystack
+            );
+        }
+    }
+}
@@ -1,20 +1,154 @@
 use std::io::{Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use serde::{Deserialize, Serialize};
 
+// Bumped on every write so values derived from settings know when to reload.
+static WRITES: AtomicU64 = AtomicU64::new(0);
+
 #[derive(Serialize, Deserialize, Debug)]
 #[allow(dead_code)]
 pub struct Settings {
     pub port: u16,
     pub mpv: Option<MpvSettings>,
+    pub vlc: Option<VlcSettings>,
+    pub mpris: Option<MprisSettings>,
+    pub mpd: Option<MpdSettings>,
+    pub kodi: Option<KodiSettings>,
     pub autohide: Option<bool>,
     pub window_size: Option<(u32, u32)>,
+    pub players: Option<Vec<PlayerSettings>>,
+    pub skip: Option<SkipSettings>,
+    // Directories the remote may open files from.
+    pub media_roots: Option<Vec<String>>,
+    pub volume: Option<VolumeSettings>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 #[allow(dead_code)]
 pub struct MpvSettings {
     pub pipe: String,
+    // Folders next to the playing file that are searched for subtitles,
+    // `Subs` when unset.
+    pub subtitle_folders: Option<Vec<String>>,
+    // Start mpv with the server, and restart it when it crashes. It listens
+    // on its own IPC path then, `pipe` is only used for an mpv started by hand.
+    pub launch: Option<bool>,
+    // `mpv` from PATH when unset.
+    pub binary: Option<String>,
+    pub args: Option<Vec<String>>,
+    // More instances next to the one on `pipe`, which is called `default`.
+    pub instances: Option<Vec<MpvInstance>>,
+    // Instance the `/mpv/*` routes control, `default` when unset.
+    pub default_instance: Option<String>,
+    // Directory searched for `mpvsocket*` sockets, each one becomes an
+    // instance named after the socket. Off unless set.
+    pub discover: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MpvInstance {
+    pub name: String,
+    pub pipe: String,
+}
+
+/// Seconds jumped by the skip buttons.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct SkipSettings {
+    pub short: f64,
+    pub long: f64,
+}
+
+impl Default for SkipSettings {
+    fn default() -> Self {
+        SkipSettings {
+            short: 10.0,
+            long: 60.0,
+        }
+    }
+}
+
+impl SkipSettings {
+    pub fn step(&self, long: bool) -> f64 {
+        if long {
+            self.long
+        } else {
+            self.short
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct VolumeSettings {
+    pub step: f64,
+    pub min: f64,
+    pub max: f64,
+    // While `night_mode` is on the volume never goes above `night_max`.
+    pub night_mode: bool,
+    pub night_max: f64,
+    // lavfi filter used to normalize loudness, `dynaudnorm` when unset.
+    pub normalize: Option<String>,
+}
+
+impl Default for VolumeSettings {
+    fn default() -> Self {
+        VolumeSettings {
+            step: 2.0,
+            min: 0.0,
+            // mpv's own default `volume-max`.
+            max: 130.0,
+            night_mode: false,
+            night_max: 50.0,
+            normalize: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VlcSettings {
+    pub host: String,
+    pub port: u16,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MpdSettings {
+    pub host: String,
+    pub port: u16,
+    pub password: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KodiSettings {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MprisSettings {
+    // Bus name without the `org.mpris.MediaPlayer2.` prefix, e.g. `spotify`.
+    // The first player on the bus is used when unset.
+    pub player: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlayerSettings {
+    pub id: String,
+    pub backend: Backend,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Mpv,
+    Vlc,
+    Mpris,
+    Mpd,
+    Kodi,
 }
 
 // Create a new settings file with default values if it doesn't exist.
@@ -34,6 +168,16 @@ pub fn get_settings_path() -> String {
     format!("{}/.config/home-remote/settings.json", home)
 }
 
+/// Directory for files the server manages itself, next to the settings file.
+pub fn get_cache_dir(name: &str) -> std::path::PathBuf {
+    let path = get_settings_path();
+    std::path::Path::new(&path)
+        .parent()
+        .unwrap()
+        .join("cache")
+        .join(name)
+}
+
 #[tauri::command]
 pub fn save_settings(settings: &str) {
     tracing::debug!("Saving settings {}", settings);
@@ -76,28 +220,34 @@ pub fn load_settings() -> Settings {
 }
 
 pub fn write_settings(settings: &Settings) {
+    if let Err(e) = try_write_settings(settings) {
+        panic!("Error writing settings: {}", e);
+    }
+}
+
+/// Like `write_settings`, but hands IO errors back instead of panicking.
+pub fn try_write_settings(settings: &Settings) -> std::io::Result<()> {
     // create directory if it doesn't exist
     let path = get_settings_path();
     let dir = std::path::Path::new(&path).parent().unwrap();
     if !dir.exists() {
-        std::fs::create_dir_all(dir).unwrap();
+        std::fs::create_dir_all(dir)?;
     }
 
-    let mut writer = std::fs::File::create(path).unwrap();
+    let mut writer = std::fs::File::create(path)?;
 
     tracing::debug!("Writing settings");
-    let pretty = serde_json::to_string_pretty(&settings);
-    if pretty.is_err() {
-        panic!("Error writing settings: {}", pretty.err().unwrap());
-    }
+    let pretty = serde_json::to_string_pretty(&settings)?;
+    writer.write_all(pretty.as_bytes())?;
+    WRITES.fetch_add(1, Ordering::Relaxed);
 
-    let write = writer.write(pretty.unwrap().as_bytes());
-
-    if write.is_err() {
-        panic!("Error writing settings: {}", write.err().unwrap());
-    }
+    tracing::debug!("Settings written: {} bytes", pretty.len());
+    Ok(())
+}
 
-    tracing::debug!("Settings written: {} bytes", write.unwrap());
+/// Number of times settings were written since the server started.
+pub fn writes() -> u64 {
+    WRITES.load(Ordering::Relaxed)
 }
 
 fn get_default_settings() -> Settings {
@@ -106,12 +256,59 @@ fn get_default_settings() -> Settings {
         #[cfg(windows)]
         mpv: Some(MpvSettings {
             pipe: r"\\.\pipe\mpvpipe".to_string(),
+            subtitle_folders: Some(vec!["Subs".to_string()]),
+            launch: None,
+            binary: None,
+            args: None,
+            instances: None,
+            default_instance: None,
+            discover: None,
         }),
         #[cfg(unix)]
         mpv: Some(MpvSettings {
             pipe: r"/tmp/mpvsocket".to_string(),
+            subtitle_folders: Some(vec!["Subs".to_string()]),
+            launch: None,
+            binary: None,
+            args: None,
+            instances: None,
+            default_instance: None,
+            discover: None,
         }),
+        vlc: None,
+        mpris: None,
+        mpd: None,
+        kodi: None,
         autohide: Some(true),
         window_size: Some((320, 600)),
+        players: Some(vec![PlayerSettings {
+            id: "mpv".to_string(),
+            backend: Backend::Mpv,
+        }]),
+        skip: Some(SkipSettings::default()),
+        media_roots: None,
+        volume: Some(VolumeSettings::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_skip_keeps_other_defaults() {
+        let skip: SkipSettings = serde_json::from_str(r#"{ "long": 120 }"#).unwrap();
+        assert_eq!(skip.short, 10.0);
+        assert_eq!(skip.long, 120.0);
+    }
+
+    #[test]
+    fn partial_volume_keeps_other_defaults() {
+        let volume: VolumeSettings = serde_json::from_str(r#"{ "max": 100 }"#).unwrap();
+        assert_eq!(volume.max, 100.0);
+        assert_eq!(volume.step, 2.0);
+        assert_eq!(volume.night_max, 50.0);
+        assert!(!volume.night_mode);
+        assert_eq!(volume.normalize, None);
     }
 }
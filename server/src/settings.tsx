@@ -19,13 +19,19 @@ import {
 import { Input } from './components/ui/input';
 import { Tooltip, TooltipContent, TooltipTrigger } from './components/ui/tooltip';
 
-const schema = z.object({
-	port: z.number().int().positive().default(7400),
-	mpv: z.object({
-		pipe: z.string(),
-	}),
-	autohide: z.boolean().optional().default(true),
-});
+// Passthrough keeps settings without a form field (players, backend configs, ...)
+// from being dropped when saving.
+const schema = z
+	.object({
+		port: z.number().int().positive().default(7400),
+		mpv: z
+			.object({
+				pipe: z.string(),
+			})
+			.passthrough(),
+		autohide: z.boolean().optional().default(true),
+	})
+	.passthrough();
 export default function SettingsPage() {
 	const form = useForm<z.infer<typeof schema>>({
 		resolver: zodResolver(schema),